@@ -0,0 +1,145 @@
+//! A minimal TUI for browsing graphs, enabled with the `interactive` feature.
+
+use std::{ffi::OsStr, process::Output};
+
+use anyhow::Context;
+use ezcmd::EasyCommand;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    widgets::{Block, List, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+use crate::{graph_cmd, post_process::LogLine, Error, GraphOptions, Result};
+
+/// Like [`crate::show_graph`], but displays the graph in a scrollable TUI.
+///
+/// Pressing `Enter` on a line shows the commit it belongs to in a separate pane. `q` quits.
+pub fn show_graph_interactive<'a, Os, Fs>(
+    options: &GraphOptions,
+    object_names: Os,
+    files: Fs,
+) -> Result<()>
+where
+    Os: IntoIterator<Item = &'a str> + Clone,
+    Fs: IntoIterator<Item = &'a OsStr> + Clone,
+{
+    let lines = graph_cmd(options, object_names, files, Some(false))?.lines()?;
+    let mut app = App {
+        lines,
+        graph: ListState::default().with_selected(Some(0)),
+        commit: None,
+    };
+    let mut terminal = ratatui::init();
+    let res = app.run(&mut terminal);
+    ratatui::restore();
+    res
+}
+
+struct App {
+    lines: Vec<LogLine>,
+    graph: ListState,
+    commit: Option<CommitPane>,
+}
+
+/// The output of `git show` for the commit selected in the graph.
+struct CommitPane {
+    lines: Vec<String>,
+    scroll: u16,
+}
+
+impl App {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal
+                .draw(|frame| self.draw(frame))
+                .context("failed to draw TUI")
+                .map_err(Error::other)?;
+            let Event::Key(key) = event::read()
+                .context("failed to read terminal event")
+                .map_err(Error::other)?
+            else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match (key.code, &mut self.commit) {
+                (KeyCode::Char('q'), _) => return Ok(()),
+                (KeyCode::Esc, Some(_)) => self.commit = None,
+                (KeyCode::Down | KeyCode::Char('j'), Some(commit)) => {
+                    commit.scroll = commit.scroll.saturating_add(1)
+                }
+                (KeyCode::Up | KeyCode::Char('k'), Some(commit)) => {
+                    commit.scroll = commit.scroll.saturating_sub(1)
+                }
+                (KeyCode::PageDown, Some(commit)) => {
+                    commit.scroll = commit.scroll.saturating_add(page_height(terminal))
+                }
+                (KeyCode::PageUp, Some(commit)) => {
+                    commit.scroll = commit.scroll.saturating_sub(page_height(terminal))
+                }
+                (KeyCode::Down | KeyCode::Char('j'), None) => self.graph.select_next(),
+                (KeyCode::Up | KeyCode::Char('k'), None) => self.graph.select_previous(),
+                (KeyCode::PageDown, None) => self.graph.scroll_down_by(page_height(terminal)),
+                (KeyCode::PageUp, None) => self.graph.scroll_up_by(page_height(terminal)),
+                (KeyCode::Enter, _) => self.show_selected_commit()?,
+                _ => (),
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame<'_>) {
+        let graph = List::new(self.lines.iter().map(|line| line.text.as_str()))
+            .block(Block::bordered().title(" graph (Enter: show commit, q: quit) "))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        match &self.commit {
+            None => frame.render_stateful_widget(graph, frame.area(), &mut self.graph),
+            Some(CommitPane { lines, scroll }) => {
+                let [graph_area, commit_area] =
+                    Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                        .areas(frame.area());
+                frame.render_stateful_widget(graph, graph_area, &mut self.graph);
+                let commit = Paragraph::new(lines.join("\n"))
+                    .block(Block::bordered().title(" commit (Esc: close) "))
+                    .scroll((*scroll, 0));
+                frame.render_widget(commit, commit_area);
+            }
+        }
+    }
+
+    /// Show the commit of the selected line, which is the closest commit line at or above it.
+    fn show_selected_commit(&mut self) -> Result<()> {
+        let Some(selected) = self.graph.selected() else {
+            return Ok(());
+        };
+        let Some(commit) = self
+            .lines
+            .get(..=selected)
+            .and_then(|lines| lines.iter().rev().find_map(|line| line.commit.as_deref()))
+        else {
+            return Ok(());
+        };
+        // Diffs needn't be UTF-8, so we can't use `stdout_lines` here.
+        let Output { stdout, status, .. } = EasyCommand::new_with("git", |cmd| {
+            cmd.args(["show", "--color=never", "--stat", "--patch", commit])
+        })
+        .output()
+        .map_err(Error::from_spawn)?;
+        Error::from_status(status)?;
+        let lines = String::from_utf8_lossy(&stdout)
+            .lines()
+            .map(ToOwned::to_owned)
+            .collect();
+        self.commit = Some(CommitPane { lines, scroll: 0 });
+        Ok(())
+    }
+}
+
+fn page_height(terminal: &DefaultTerminal) -> u16 {
+    terminal
+        .size()
+        .map_or(1, |size| size.height.saturating_sub(2))
+}
@@ -1,11 +1,28 @@
+#[cfg(feature = "interactive")]
+mod interactive;
+mod post_process;
+
 use std::{
+    collections::{btree_map, BTreeMap, BTreeSet},
+    env,
     ffi::OsStr,
-    io::{self, Cursor},
-    process::{exit, Command, ExitStatus, Output},
+    fs::{self, File, OpenOptions},
+    io::{self, Cursor, IsTerminal, Write},
+    path::PathBuf,
+    process::{self, exit, Command, ExitStatus, Output, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{anyhow, Context};
+use clap::{Parser, ValueEnum};
 use ezcmd::{EasyCommand, ExecuteError, RunErrorKind};
+use post_process::LogLine;
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "interactive")]
+pub use interactive::show_graph_interactive;
 
 pub fn run<F>(f: F)
 where
@@ -16,6 +33,21 @@ where
         Ok(()) => (),
         Err(e) => match e {
             Error::SubprocessFailedWithExplanation { code } => exit(code.unwrap_or(255)),
+            Error::GitNotFound => {
+                log::error!("could not find `git` on `PATH`; is Git installed?");
+                exit(127);
+            }
+            Error::EmptyGraph => {
+                log::info!("no commits to show besides the merge base");
+                exit(3);
+            }
+            Error::ShallowHistory => {
+                log::error!(
+                    "failed to find a merge base, probably because this is a shallow clone; \
+                    consider running `git fetch --unshallow`"
+                );
+                exit(254);
+            }
             Error::Other { source } => {
                 log::error!("{source:?}");
                 exit(254);
@@ -33,8 +65,20 @@ fn init_logger() {
 
 #[derive(Debug)]
 pub enum Error {
-    SubprocessFailedWithExplanation { code: Option<i32> },
-    Other { source: anyhow::Error },
+    SubprocessFailedWithExplanation {
+        code: Option<i32>,
+    },
+    /// `git` couldn't be spawned because it wasn't found.
+    GitNotFound,
+    /// No merge base could be found in a shallow clone, whose history may have been truncated
+    /// before it.
+    ShallowHistory,
+    /// There were no commits to show besides the merge base, and
+    /// [`GraphOptions::require_nonempty`] was set.
+    EmptyGraph,
+    Other {
+        source: anyhow::Error,
+    },
 }
 
 impl Error {
@@ -51,11 +95,26 @@ impl Error {
         }
     }
 
+    /// Convert an error from running a command, singling out when `git` itself can't be found.
+    fn from_spawn(source: impl Into<anyhow::Error>) -> Self {
+        let source = source.into();
+        let not_found = source.chain().any(|e| {
+            e.downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::NotFound)
+        });
+        if not_found {
+            log::debug!("{source:?}");
+            Self::GitNotFound
+        } else {
+            Self::other(source)
+        }
+    }
+
     fn from_run(source: ExecuteError<RunErrorKind>) -> Self {
         let ExecuteError { source, .. } = source;
         // TODO: Not super happy about basically cloning this.
         match source {
-            RunErrorKind::SpawnAndWait(e) => Self::other(e.into()),
+            RunErrorKind::SpawnAndWait(e) => Self::from_spawn(e),
             RunErrorKind::UnsuccessfulExitCode { code } => {
                 Self::SubprocessFailedWithExplanation { code }
             }
@@ -77,26 +136,617 @@ impl From<ExecuteError<RunErrorKind>> for Error {
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-pub fn show_graph<'a, Os, Fs>(format: Option<String>, object_names: Os, files: Fs) -> Result<()>
+/// The format used by [`show_graph`] when none is specified or configured: one line per commit,
+/// with its decorations, subject, author, and age.
+pub const DEFAULT_FORMAT: &str = "%C(auto)%h%d %s %C(dim)%an, %ar%C(reset)";
+
+/// [`DEFAULT_FORMAT`], but with the committer date instead of the author date, for
+/// [`GraphOptions::committer_date`].
+pub const COMMITTER_DATE_FORMAT: &str = "%C(auto)%h%d %s %C(dim)%an, %cr%C(reset)";
+
+/// The format chosen by [`GraphOptions::responsive_format`] for narrow terminals: like
+/// [`DEFAULT_FORMAT`], but without the author and age.
+pub const TERSE_FORMAT: &str = "%C(auto)%h%d %s";
+
+/// The width of a terminal, in columns, below which [`GraphOptions::responsive_format`] chooses
+/// [`TERSE_FORMAT`].
+const RESPONSIVE_FORMAT_MIN_WIDTH: u16 = 100;
+
+/// Options controlling how [`show_graph`] renders its output.
+#[derive(Clone, Debug, Default)]
+pub struct GraphOptions {
+    /// The `--format` argument for `git log`. Falls back to the `glimpse.pretty` config, and then
+    /// to [`DEFAULT_FORMAT`] (see also [`Self::responsive_format`]).
+    pub format: Option<String>,
+    /// When no format is specified or configured, choose [`TERSE_FORMAT`] instead of
+    /// [`DEFAULT_FORMAT`] if `stdout` isn't a terminal at least 100 columns wide.
+    pub responsive_format: bool,
+    /// When no format is specified or configured, use [`COMMITTER_DATE_FORMAT`] instead of
+    /// [`DEFAULT_FORMAT`], i.e., to see when rebased commits landed.
+    pub committer_date: bool,
+    /// Forward `--show-signature` to `git log`, annotating commits with signature verification.
+    pub show_signature: bool,
+    /// Show the abbreviated object names of each commit's parents. These are appended to custom
+    /// formats, and shown via `git log --parents` for named formats.
+    pub show_parents: bool,
+    /// The commit at which to cut off history. Defaults to the octopus merge base of all selected
+    /// object names.
+    pub merge_base: Option<String>,
+    /// Show only history newer than these commits, excluding them and their ancestors, instead of
+    /// cutting off history at [`Self::merge_base`]. Unlike a merge base, this doesn't limit
+    /// history to descendants of the cutoff. If empty, all history of the selected object names
+    /// is shown.
+    pub since: Option<Vec<String>>,
+    /// Ignore the `glimpse.decorateRefsExclude` config, decorating commits with all refs.
+    pub decorate_all: bool,
+    /// Also decorate commits with refs matching these patterns (see `git log --decorate-refs`).
+    /// By default, Git only decorates commits with branches, remote-tracking branches, tags,
+    /// and `HEAD`.
+    pub decorate_refs: Vec<String>,
+    /// Prefix the line of the commit at `HEAD` with a marker. The marker is configured with
+    /// `glimpse.headMarker`, which also enables this when set.
+    pub mark_head: bool,
+    /// Prefix the line of the commit at which history is cut off (see [`Self::merge_base`]) with a
+    /// `base` marker.
+    pub mark_base: bool,
+    /// Disable all embellishments of Git's output, even those enabled via configuration.
+    pub plain: bool,
+    /// Markers with which to prefix the lines of specific commits.
+    pub markers: Vec<CommitMarker>,
+    /// Print a key explaining the markers and other embellishments in use before the graph. Falls
+    /// back to the `glimpse.legend` config, unless [`Self::plain`] is set.
+    pub legend: bool,
+    /// Color author names and emails consistently per author, when output is colored. This
+    /// requires a custom format including `%an` or `%ae`.
+    pub color_by_author: bool,
+    /// Only show commits that touch all of the files specified to [`show_graph`], rather than any
+    /// of them. This runs `git rev-list` once per file, and shows commits without their history
+    /// or a graph (see `git log --no-walk`).
+    pub all_paths: bool,
+    /// Only show commits whose author matches this basic regular expression (see
+    /// `git log --author`).
+    pub author: Option<String>,
+    /// Hide commits whose author matches any of these basic regular expressions, like those of
+    /// bots. `git log` can't exclude authors itself, so this lists commits with `git rev-list`
+    /// beforehand, and shows the remainder without their history or a graph, like
+    /// [`Self::all_paths`].
+    pub exclude_authors: Vec<String>,
+    /// The encoding into which commit messages are re-encoded (see `git log --encoding`).
+    /// Defaults to UTF-8.
+    pub encoding: Option<String>,
+    /// Stop walking history this many commits from each selected object name. This is a
+    /// guardrail against graphing enormous amounts of history, i.e., due to a bad base.
+    pub max_depth: Option<usize>,
+    /// Show at most this many commits (see `git log --max-count`). Unlike [`Self::max_depth`], this
+    /// doesn't affect which commits are selected, i.e., the merge base, only how many are shown.
+    pub max_count: Option<usize>,
+    /// Dim the lines of commits committed longer ago than this, when output is colored. This
+    /// requires a custom format (see [`Self::format`]).
+    pub dim_older_than: Option<Duration>,
+    /// Hide commits committed longer ago than this (see `git log --since`).
+    pub hide_older_than: Option<Duration>,
+    /// Only show commits committed after this date, in any format that `git log --since`
+    /// accepts, like `2 weeks ago`. This doesn't affect the merge base.
+    pub since_date: Option<String>,
+    /// Only show commits committed before this date, in any format that `git log --until`
+    /// accepts. This doesn't affect the merge base.
+    pub until_date: Option<String>,
+    /// Don't color decorations, while still coloring the rest of each commit's line.
+    pub plain_decorations: bool,
+    /// Show a line above that of the commit at `HEAD` if there are uncommitted changes in the
+    /// working tree, with the number of changed files.
+    pub wip: bool,
+    /// Mark commits without a `Signed-off-by` trailer, and print how many there are after the
+    /// graph.
+    pub check_signoff: bool,
+    /// Mark merge commits that combine divergent history, i.e., whose parents aren't ancestors of
+    /// one another, as opposed to those that could have been fast-forwards.
+    pub mark_true_merges: bool,
+    /// Mark commits that aren't reachable from any remote-tracking branch, i.e., those that
+    /// pushing would publish.
+    pub mark_unpushed: bool,
+    /// Label each commit with its position in the stack, counting from the merge base, like
+    /// `[3/7]`. Positions follow topological order when history isn't linear.
+    pub stack_position: bool,
+    /// Label each `fixup!`, `squash!`, or `amend!` commit with the commit it targets, like
+    /// `→ 1a2b3c4`, previewing what `git rebase --autosquash` would do.
+    pub resolve_fixups: bool,
+    /// Truncate commit subjects (i.e., `%s`) to this many characters, ending truncated subjects with
+    /// an ellipsis. This requires a custom format (see [`Self::format`]).
+    pub truncate_subject: Option<usize>,
+    /// Show relative dates (i.e., `%ar` and `%cr`) in this language, rather than Git's English.
+    /// Dates are computed from Unix timestamps instead, so they approximate Git's rounding.
+    pub relative_locale: Option<RelativeLocale>,
+    /// Don't ask for confirmation before showing more commits than the
+    /// `glimpse.warnCommitThreshold` config allows (1000 by default). Confirmation is only asked
+    /// for when `stdin` and `stderr` are terminals.
+    pub assume_yes: bool,
+    /// Fail with [`Error::EmptyGraph`] instead of showing a graph with no commits besides the
+    /// merge base.
+    pub require_nonempty: bool,
+    /// When `stdout` is a terminal, show the most recent commits at `HEAD` right away, and
+    /// replace them with the full graph once it's ready, i.e., once the merge base is found.
+    pub progressive: bool,
+    /// How to render output, i.e., from [`resolve_render_config`].
+    pub render: RenderConfig,
+}
+
+/// Settings for rendering a graph, resolved by [`resolve_render_config`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RenderConfig {
+    pub graph_style: GraphStyle,
+    pub color: ColorChoice,
+    /// Whether to page output. Git still only pages output if `stdout` is a terminal.
+    pub pager: bool,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            graph_style: GraphStyle::default(),
+            color: ColorChoice::default(),
+            pager: true,
+        }
+    }
+}
+
+/// The characters with which the graph is drawn.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum GraphStyle {
+    /// Git's own ASCII art.
+    #[default]
+    Ascii,
+    /// Box-drawing characters, like `│` and `╱`.
+    Unicode,
+}
+
+/// A language in which to show relative dates (see [`GraphOptions::relative_locale`]).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum RelativeLocale {
+    /// German.
+    De,
+    /// English, like Git itself.
+    En,
+    /// Spanish.
+    Es,
+    /// French.
+    Fr,
+}
+
+/// When to color output, like Git's own `--color` options.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum ColorChoice {
+    /// Let Git decide, according to its `color.*` configuration, if `stdout` is a terminal.
+    /// Otherwise, don't color, even if Git is configured to always color.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Resolve how to render graphs, preferring the settings specified (i.e., from flags), then the
+/// `glimpse.graphStyle`, `glimpse.color`, and `glimpse.pager` configs, then defaults.
+pub fn resolve_render_config(
+    graph_style: Option<GraphStyle>,
+    color: Option<ColorChoice>,
+    pager: Option<bool>,
+) -> Result<RenderConfig> {
+    fn from_config<T: ValueEnum>(path: &str) -> Result<Option<T>> {
+        git_config(path)?
+            .map(|value| {
+                T::from_str(&value, true).map_err(|e| {
+                    Error::other(anyhow!("invalid value {value:?} for `{path}` config: {e}"))
+                })
+            })
+            .transpose()
+    }
+
+    let defaults = RenderConfig::default();
+    let render = RenderConfig {
+        graph_style: match graph_style {
+            Some(graph_style) => graph_style,
+            None => from_config("glimpse.graphStyle")?.unwrap_or(defaults.graph_style),
+        },
+        color: match color {
+            Some(color) => color,
+            None => from_config("glimpse.color")?.unwrap_or(defaults.color),
+        },
+        pager: match pager {
+            Some(pager) => pager,
+            None => git_config_bool("glimpse.pager")?.unwrap_or(defaults.pager),
+        },
+    };
+    log::trace!("resolved render config: {render:?}");
+    Ok(render)
+}
+
+/// A marker prefixed to the line of a specific commit in a graph.
+#[derive(Clone, Debug)]
+pub struct CommitMarker {
+    /// The full object name of the commit to mark.
+    pub commit: String,
+    pub marker: String,
+    pub color: Option<MarkerColor>,
+    /// What the marker means, for the legend (see [`GraphOptions::legend`]).
+    pub description: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MarkerColor {
+    Red,
+    Green,
+    Yellow,
+}
+
+pub fn show_graph<'a, Os, Fs>(options: &GraphOptions, object_names: Os, files: Fs) -> Result<()>
 where
     Os: IntoIterator<Item = &'a str> + Clone,
     Fs: IntoIterator<Item = &'a OsStr> + Clone,
 {
-    let merge_base = {
-        let mut output = stdout_lines(EasyCommand::new_with("git", |cmd| {
-            cmd.args(["merge-base", "--octopus"])
-                .args(object_names.clone().into_iter())
-        }))?;
-        if output.len() != 1 {
-            return Err(Error::other(anyhow!(
-                "expected a single line of output, but got {}; \
-                output: {output:#?}",
-                output.len()
-            )));
+    let GraphOptions {
+        mark_head,
+        mark_base,
+        plain,
+        markers,
+        render,
+        dim_older_than,
+        color_by_author,
+        legend,
+        truncate_subject,
+        relative_locale,
+        check_signoff,
+        mark_true_merges,
+        mark_unpushed,
+        stack_position,
+        resolve_fixups,
+        wip,
+        progressive,
+        ..
+    } = options;
+    if *progressive && io::stdout().is_terminal() {
+        show_preview(options)?;
+    }
+    let mut markers = if *plain { Vec::new() } else { markers.clone() };
+    if !plain {
+        let head_marker =
+            git_config("glimpse.headMarker")?.or_else(|| mark_head.then(|| ">".to_owned()));
+        if let Some(marker) = head_marker {
+            markers.push(CommitMarker {
+                commit: rev_parse("HEAD")?,
+                marker,
+                color: None,
+                description: "the commit at `HEAD`".to_owned(),
+            });
         }
-        output.pop().unwrap()
+    }
+    let mark_base = *mark_base && !plain;
+    let unicode_graph = render.graph_style == GraphStyle::Unicode;
+    let dim_older_than = dim_older_than.filter(|_| !plain);
+    let check_signoff = *check_signoff && !plain;
+    let mark_true_merges = *mark_true_merges && !plain;
+    let mark_unpushed = *mark_unpushed && !plain;
+    let stack_position = *stack_position && !plain;
+    let resolve_fixups = *resolve_fixups && !plain;
+    let wip = *wip && !plain;
+    let link_commits =
+        !plain && io::stdout().is_terminal() && git_config("glimpse.commitUrlTemplate")?.is_some();
+    let legend = *legend || (!plain && git_config_bool("glimpse.legend")?.unwrap_or(false));
+    let post_processing = !markers.is_empty()
+        || mark_base
+        || unicode_graph
+        || dim_older_than.is_some()
+        || (*color_by_author && !plain)
+        || legend
+        || truncate_subject.is_some()
+        || relative_locale.is_some()
+        || check_signoff
+        || mark_true_merges
+        || mark_unpushed
+        || stack_position
+        || resolve_fixups
+        || link_commits
+        || wip;
+    let capture = if post_processing {
+        Some(match render.color {
+            ColorChoice::Auto => post_process::should_color()?,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        })
+    } else {
+        None
     };
+    let mut graph_cmd = graph_cmd(options, object_names, files, capture)?;
+    if !post_processing {
+        clear_preview();
+        return graph_cmd
+            .cmd
+            .spawn_and_wait()
+            .map_err(Error::from_spawn)
+            .and_then(Error::from_status);
+    }
+    let mut lines = graph_cmd.lines()?;
+
+    let mut summary = None;
+    if check_signoff {
+        let commits = lines
+            .iter()
+            .filter_map(|line| line.commit.as_deref())
+            .collect::<Vec<_>>();
+        let unsigned = commits_without_signoff(commits.iter().copied())?;
+        summary = Some(format!(
+            "{} of {} commits have no `Signed-off-by` trailer",
+            unsigned.len(),
+            commits.len()
+        ));
+        markers.extend(unsigned.into_iter().map(|commit| CommitMarker {
+            commit,
+            marker: "unsigned".to_owned(),
+            color: Some(MarkerColor::Red),
+            description: "a commit without a `Signed-off-by` trailer".to_owned(),
+        }));
+    }
+    if mark_true_merges {
+        let commits = lines.iter().filter_map(|line| line.commit.as_deref());
+        markers.extend(
+            true_merges(commits)?
+                .into_iter()
+                .map(|commit| CommitMarker {
+                    commit,
+                    marker: "merge".to_owned(),
+                    color: Some(MarkerColor::Green),
+                    description:
+                        "a merge of divergent history, which couldn't have been a fast-forward"
+                            .to_owned(),
+                }),
+        );
+    }
+    if mark_unpushed {
+        let commits = lines.iter().filter_map(|line| line.commit.as_deref());
+        markers.extend(
+            unpushed_commits(commits, graph_cmd.merge_base.as_deref())?
+                .into_iter()
+                .map(|commit| CommitMarker {
+                    commit,
+                    marker: "local".to_owned(),
+                    color: Some(MarkerColor::Yellow),
+                    description: "a commit not reachable from any remote-tracking branch"
+                        .to_owned(),
+                }),
+        );
+    }
+    if let Some(merge_base) = graph_cmd.merge_base.clone().filter(|_| mark_base) {
+        markers.push(CommitMarker {
+            commit: merge_base,
+            marker: "base".to_owned(),
+            color: None,
+            description: "the merge base, below which history is cut off".to_owned(),
+        });
+    }
+    if unicode_graph {
+        for line in &mut lines {
+            line.text = post_process::unicode_graph(&line.text);
+        }
+    }
+    if graph_cmd.authors_marked {
+        for line in &mut lines {
+            line.text = post_process::color_authors(&line.text);
+        }
+    }
+    let mut effects = Vec::new();
+    // These aren't included in the legend, which would otherwise list each position.
+    let mut labels = Vec::new();
+    if stack_position {
+        let commits = lines
+            .iter()
+            .filter_map(|line| line.commit.as_deref())
+            .filter(|commit| Some(*commit) != graph_cmd.merge_base.as_deref())
+            .collect::<Vec<_>>();
+        if !is_linear(commits.iter().copied())? {
+            log::warn!(
+                "history isn't linear, so stack positions are ambiguous; numbering commits in \
+                topological order"
+            );
+        }
+        let total = commits.len();
+        let width = total.to_string().len();
+        labels.extend(
+            commits
+                .iter()
+                .enumerate()
+                .map(|(idx, commit)| CommitMarker {
+                    commit: (*commit).to_owned(),
+                    marker: format!("[{:>width$}/{total}]", total - idx),
+                    color: None,
+                    description: String::new(),
+                }),
+        );
+        effects.push("`[i/n]` labels are positions in the stack, counting from the merge base");
+    }
+    if resolve_fixups {
+        let commits = lines
+            .iter()
+            .filter_map(|line| line.commit.as_deref())
+            .filter(|commit| Some(*commit) != graph_cmd.merge_base.as_deref());
+        let targets = fixup_targets(commits)?;
+        if !targets.is_empty() {
+            labels.extend(targets.into_iter().map(|(commit, target)| CommitMarker {
+                commit,
+                marker: format!("→ {target}"),
+                color: None,
+                description: String::new(),
+            }));
+            effects.push("`→` labels point at the commits that fixups would be squashed into");
+        }
+    }
+    if graph_cmd.authors_marked {
+        effects.push("authors are colored consistently per author");
+    }
+    if let Some(age) = dim_older_than {
+        if !graph_cmd.marked {
+            log::warn!("dimming old commits requires a custom format, not dimming");
+        } else if capture == Some(true) {
+            post_process::dim_commits_before(&mut lines, cutoff(age));
+            effects.push("dimmed commits are older than the `--dim-older-than` cutoff");
+        }
+    }
+    if wip {
+        let changes = stdout_lines(
+            EasyCommand::new_with("git", |cmd| cmd.args(["status", "--porcelain"])),
+            false,
+        )?
+        .len();
+        if changes > 0
+            && post_process::insert_working_tree(&mut lines, &rev_parse("HEAD")?, changes)
+        {
+            effects.push("`+` marks uncommitted changes in the working tree, above `HEAD`");
+        }
+    }
+    let prefixes = markers.iter().chain(&labels).cloned().collect::<Vec<_>>();
+    post_process::prefix_markers(&mut lines, &prefixes, capture == Some(true));
+    let mut rendered = String::new();
+    if legend {
+        if let Some(legend) = post_process::legend(&markers, &effects, capture == Some(true)) {
+            rendered.push_str(&legend);
+            rendered.push('\n');
+        }
+    }
+    for line in lines {
+        rendered.push_str(&line.text);
+        rendered.push('\n');
+    }
+    if let Some(summary) = summary {
+        rendered.push('\n');
+        rendered.push_str(&summary);
+        rendered.push('\n');
+    }
+    clear_preview();
+    if render.pager {
+        post_process::page(rendered.as_bytes())
+    } else {
+        post_process::write_stdout(rendered.as_bytes())
+    }
+}
+
+/// A `git log --graph` invocation built by [`graph_cmd`].
+struct GraphCmd {
+    cmd: EasyCommand,
+    /// Whether the format was extended with [`post_process::mark_commits`].
+    marked: bool,
+    /// Whether the format was extended with [`post_process::mark_authors`].
+    authors_marked: bool,
+    /// The number of characters to truncate subjects to, if the format was extended with
+    /// [`post_process::mark_subjects`].
+    truncate_subjects: Option<usize>,
+    /// The language to show relative dates in, if the format was extended with
+    /// [`post_process::mark_relative_dates`].
+    relative_locale: Option<RelativeLocale>,
+    /// The `glimpse.commitUrlTemplate` config to link object names with, if the format was
+    /// extended with [`post_process::mark_hashes`].
+    commit_url_template: Option<String>,
+    /// The full object name of the commit at which history is cut off, unless
+    /// [`GraphOptions::since`] was specified, or selected commits have no common ancestor.
+    merge_base: Option<String>,
+    /// Kept alive for as long as `cmd` may read from it.
+    _revisions: Revisions,
+}
+
+impl GraphCmd {
+    /// Run this command, capturing its output as lines.
+    fn lines(&mut self) -> Result<Vec<LogLine>> {
+        let Output { stdout, status, .. } = self.cmd.output().map_err(Error::from_spawn)?;
+        Error::from_status(status)?;
+        let now = cutoff(Duration::ZERO);
+        Ok(String::from_utf8_lossy(&stdout)
+            .lines()
+            .map(|line| {
+                let mut line = LogLine::parse(line, self.marked);
+                if let Some(max_chars) = self.truncate_subjects {
+                    line.text = post_process::truncate_subjects(&line.text, max_chars);
+                }
+                if let Some(locale) = self.relative_locale {
+                    line.text = post_process::localize_relative_dates(&line.text, locale, now);
+                }
+                if let Some(template) = &self.commit_url_template {
+                    let url = line
+                        .commit
+                        .as_deref()
+                        .map(|commit| template.replace("%H", commit));
+                    line.text = post_process::link_hashes(&line.text, url.as_deref());
+                }
+                line
+            })
+            .collect())
+    }
+}
+
+/// The slots of `color.decorate.<slot>` configuration for Git.
+const DECORATION_COLOR_SLOTS: &[&str] = &[
+    "branch",
+    "remoteBranch",
+    "tag",
+    "stash",
+    "HEAD",
+    "grafted",
+    "symbol",
+];
+
+/// Build the `git log --graph` invocation for [`show_graph`].
+///
+/// If `capture` is `Some`, the command's output is intended to be captured and post-processed,
+/// rather than written directly to `stdout`, and its value determines whether output is colored.
+fn graph_cmd<'a, Os, Fs>(
+    options: &GraphOptions,
+    object_names: Os,
+    files: Fs,
+    capture: Option<bool>,
+) -> Result<GraphCmd>
+where
+    Os: IntoIterator<Item = &'a str> + Clone,
+    Fs: IntoIterator<Item = &'a OsStr> + Clone,
+{
+    let GraphOptions {
+        format,
+        responsive_format,
+        committer_date,
+        show_signature,
+        show_parents,
+        merge_base: _,
+        since: _,
+        decorate_all,
+        decorate_refs,
+        mark_head: _,
+        mark_base: _,
+        plain,
+        markers: _,
+        legend: _,
+        color_by_author,
+        all_paths: _,
+        author,
+        exclude_authors: _,
+        encoding,
+        max_depth: _,
+        max_count,
+        render,
+        dim_older_than: _,
+        hide_older_than: _,
+        since_date: _,
+        until_date: _,
+        plain_decorations,
+        assume_yes,
+        require_nonempty,
+        truncate_subject,
+        relative_locale,
+        check_signoff: _,
+        mark_true_merges: _,
+        mark_unpushed: _,
+        stack_position: _,
+        resolve_fixups: _,
+        wip: _,
+        progressive: _,
+    } = options;
+    let selection = select_history(options, object_names, files)?;
     let format = format
+        .clone()
         .map(Ok)
         .or_else(|| {
             git_config("glimpse.pretty")
@@ -108,86 +758,3143 @@ where
                         );
                     } else {
                         log::trace!(
-                            "no format specified, no format found in `glimpse.pretty` config"
+                            "no format specified, no format found in `glimpse.pretty` config, \
+                            using default format"
                         );
                     }
                     configged
                 })
                 .transpose()
         })
-        .transpose()?;
-    EasyCommand::new_with("git", |cmd| {
-        cmd.args(["log", "--graph", "--decorate"]);
-        if let Some(format) = format {
-            cmd.arg(format!("--format={format}"));
+        .transpose()?
+        .unwrap_or_else(|| {
+            let default = default_format(*committer_date);
+            if !responsive_format {
+                return default.to_owned();
+            }
+            let width = terminal_size::terminal_size().map(|(width, _height)| width.0);
+            log::trace!("choosing format responsively for terminal width {width:?}");
+            if width.is_some_and(|width| width >= RESPONSIVE_FORMAT_MIN_WIDTH) {
+                default
+            } else {
+                TERSE_FORMAT
+            }
+            .to_owned()
+        });
+    let format = auto_decorate(format)?;
+    let (format, parents_flag) = if *show_parents {
+        match post_process::append_to_format(&format, " %C(dim)parents: %p%C(reset)") {
+            Some(format) => (format, false),
+            None => (format, true),
+        }
+    } else {
+        (format, false)
+    };
+    let decorate_refs_exclude = if *decorate_all {
+        Vec::new()
+    } else {
+        git_config_all("glimpse.decorateRefsExclude")?
+    };
+    let color_authors = *color_by_author && !plain && capture == Some(true);
+    let (format, authors_marked) = match color_authors.then(|| post_process::mark_authors(&format))
+    {
+        Some(Some(marked)) => (marked, true),
+        Some(None) => {
+            log::warn!("coloring by author requires a format including `%an` or `%ae`");
+            (format, false)
+        }
+        None => (format, false),
+    };
+    let (format, truncate_subjects) = match truncate_subject
+        .filter(|_| capture.is_some())
+        .map(|max_chars| (max_chars, post_process::mark_subjects(&format)))
+    {
+        Some((max_chars, Some(marked))) => (marked, Some(max_chars)),
+        Some((_, None)) => {
+            log::warn!("truncating subjects requires a format including `%s`");
+            (format, None)
+        }
+        None => (format, None),
+    };
+    let (format, relative_locale) = match relative_locale
+        .filter(|_| capture.is_some())
+        .map(|locale| (locale, post_process::mark_relative_dates(&format)))
+    {
+        Some((locale, Some(marked))) => (marked, Some(locale)),
+        Some((_, None)) => {
+            log::warn!("localizing relative dates requires a format including `%ar` or `%cr`");
+            (format, None)
         }
-        cmd.arg("--ancestry-path")
-            .arg(format!("^{merge_base}^@"))
-            .args(object_names.clone().into_iter())
+        None => (format, None),
+    };
+    let commit_url_template = if !plain && capture == Some(true) && io::stdout().is_terminal() {
+        git_config("glimpse.commitUrlTemplate")?
+    } else {
+        None
+    };
+    let (format, commit_url_template) =
+        match commit_url_template.map(|template| (template, post_process::mark_hashes(&format))) {
+            Some((template, Some(marked))) => (marked, Some(template)),
+            Some((_, None)) => {
+                log::warn!("linking commits requires a custom format including `%h` or `%H`");
+                (format, None)
+            }
+            None => (format, None),
+        };
+    let marked_format = capture
+        .and(Some(&*format))
+        .and_then(post_process::mark_commits);
+    let confirm = !assume_yes && io::stdin().is_terminal() && io::stderr().is_terminal();
+    if confirm || *require_nonempty {
+        let commits = if selection.no_walk {
+            selection.revisions.revisions.clone()
+        } else {
+            rev_list(
+                &selection.revisions,
+                &|cmd| selection.limit_history(cmd),
+                &selection.files,
+                &|cmd| {
+                    if let Some(author) = author {
+                        cmd.arg("--basic-regexp").arg(format!("--author={author}"));
+                    }
+                },
+            )?
+        };
+        if *require_nonempty
+            && commits
+                .iter()
+                .all(|commit| Some(commit) == selection.merge_base.as_ref())
+        {
+            return Err(Error::EmptyGraph);
+        }
+        if confirm {
+            confirm_commit_count(max_count.map_or(commits.len(), |max| commits.len().min(max)))?;
+        }
+    }
+    let cmd = EasyCommand::new_with("git", |cmd| {
+        if *plain_decorations {
+            for slot in DECORATION_COLOR_SLOTS {
+                cmd.arg("-c").arg(format!("color.decorate.{slot}=normal"));
+            }
+        }
+        let to_terminal = io::stdout().is_terminal();
+        if !render.pager || !to_terminal {
+            cmd.arg("--no-pager");
+        }
+        cmd.arg("log");
+        // Git can't draw a graph of commits without their history.
+        if !selection.no_walk {
+            cmd.arg("--graph");
+        }
+        cmd.arg("--decorate");
+        let encoding = encoding.as_deref().unwrap_or("UTF-8");
+        cmd.arg(format!("--encoding={encoding}"));
+        let color = capture.or(match render.color {
+            ColorChoice::Auto => (!to_terminal).then_some(false),
+            ColorChoice::Always => Some(true),
+            ColorChoice::Never => Some(false),
+        });
+        if let Some(color) = color {
+            cmd.arg(if color {
+                "--color=always"
+            } else {
+                "--color=never"
+            });
+        }
+        if !decorate_refs.is_empty() {
+            // Specifying any patterns replaces Git's default set of them, so include those, too.
+            let defaults = ["refs/heads/", "refs/remotes/", "refs/tags/", "HEAD"];
+            for pattern in defaults
+                .iter()
+                .copied()
+                .chain(decorate_refs.iter().map(String::as_str))
+            {
+                cmd.arg(format!("--decorate-refs={pattern}"));
+            }
+        }
+        for pattern in decorate_refs_exclude {
+            cmd.arg(format!("--decorate-refs-exclude={pattern}"));
+        }
+        let format = marked_format.as_ref().unwrap_or(&format);
+        cmd.arg(format!("--format={format}"));
+        if *show_signature {
+            cmd.arg("--show-signature");
+        }
+        if parents_flag {
+            cmd.arg("--parents");
+        }
+        selection.pass_to(cmd).stderr(Stdio::inherit())
+    });
+    let HistorySelection {
+        merge_base,
+        revisions,
+        ..
+    } = selection;
+    Ok(GraphCmd {
+        cmd,
+        marked: marked_format.is_some(),
+        authors_marked,
+        truncate_subjects,
+        relative_locale,
+        commit_url_template,
+        merge_base,
+        _revisions: revisions,
+    })
+}
+
+/// The commits that [`show_graph`] selects for `object_names` and `files` with `options`, before
+/// rendering them.
+struct HistorySelection<'f> {
+    /// The full object name of the commit at which history is cut off, unless
+    /// [`GraphOptions::since`] was specified or the selected commits have no common ancestor.
+    merge_base: Option<String>,
+    revisions: Revisions,
+    /// Whether `revisions` are exactly the commits to show, rather than tips of history to walk.
+    no_walk: bool,
+    /// Arguments limiting the history walked from `revisions`, like `--since`.
+    limits: Vec<String>,
+    author: Option<String>,
+    max_count: Option<usize>,
+    files: Vec<&'f OsStr>,
+}
+
+impl HistorySelection<'_> {
+    fn limit_history(&self, cmd: &mut Command) {
+        cmd.args(&self.limits);
+    }
+
+    /// Select these commits with `cmd`, a `git log` invocation, after any of its other options.
+    fn pass_to<'c>(&self, cmd: &'c mut Command) -> &'c mut Command {
+        if let Some(author) = &self.author {
+            cmd.arg("--basic-regexp").arg(format!("--author={author}"));
+        }
+        if self.no_walk {
+            cmd.arg("--no-walk=unsorted");
+        } else {
+            self.limit_history(cmd);
+        }
+        if let Some(max_count) = self.max_count {
+            cmd.arg(format!("--max-count={max_count}"));
+        }
+        self.revisions
+            .pass_to(cmd)
             .arg("--") // Make it unambiguous that we're specifying branches first
-            .args(files)
+            .args(&self.files)
+    }
+}
+
+/// Select the commits to show for `object_names` and `files` with `options`, i.e., for
+/// [`graph_cmd`] and structured outputs alike, so that they always agree.
+fn select_history<'a, 'f, Os, Fs>(
+    options: &GraphOptions,
+    object_names: Os,
+    files: Fs,
+) -> Result<HistorySelection<'f>>
+where
+    Os: IntoIterator<Item = &'a str> + Clone,
+    Fs: IntoIterator<Item = &'f OsStr>,
+{
+    let GraphOptions {
+        merge_base,
+        since,
+        all_paths,
+        author,
+        exclude_authors,
+        max_depth,
+        max_count,
+        hide_older_than,
+        since_date,
+        until_date,
+        ..
+    } = options;
+    if is_shallow_repository()? {
+        log::warn!(
+            "this is a shallow clone, so the graph may be incomplete; consider running \
+            `git fetch --unshallow`"
+        );
+    }
+    let (merge_base, bottoms) = match since {
+        Some(since) => {
+            let bottoms = since
+                .iter()
+                .map(|since| rev_parse(since).map(|since| format!("^{since}")))
+                .collect::<Result<Vec<_>>>()?;
+            (None, bottoms)
+        }
+        None => {
+            let merge_base = match merge_base {
+                Some(merge_base) => Some(merge_base.clone()),
+                None => self::merge_base(object_names.clone())?,
+            };
+            let bottoms = match &merge_base {
+                Some(merge_base) => merge_base_bottoms(merge_base)?,
+                None => Vec::new(),
+            };
+            (merge_base, bottoms)
+        }
+    };
+    // Without any bottoms, i.e., for a root merge base or when there's no common ancestor, Git
+    // refuses `--ancestry-path`, and all history is shown anyway.
+    let ancestry_path = !bottoms.is_empty();
+    let bottoms = match max_depth {
+        Some(max_depth) => depth_bottoms(*max_depth, bottoms, object_names.clone())?,
+        None => bottoms,
+    };
+    let revisions = Revisions::new(bottoms, object_names)?;
+    let mut limits = Vec::new();
+    if let Some(age) = hide_older_than {
+        limits.push(format!("--since=@{}", cutoff(*age)));
+    }
+    if let Some(date) = since_date {
+        limits.push(format!("--since={date}"));
+    }
+    if let Some(date) = until_date {
+        limits.push(format!("--until={date}"));
+    }
+    // `--since` stops walking history before reaching the merge base, which would leave no
+    // commits on its ancestry path.
+    if since.is_none() && hide_older_than.is_none() && since_date.is_none() && ancestry_path {
+        limits.push("--ancestry-path".to_owned());
+    }
+    let limit_history = |cmd: &mut Command| {
+        cmd.args(&limits);
+    };
+    let files = files.into_iter().collect::<Vec<_>>();
+    let all_paths = *all_paths && files.len() > 1;
+    let (revisions, no_walk) = if all_paths || !exclude_authors.is_empty() {
+        let mut commits = if all_paths {
+            let commits = commits_touching_all(&revisions, &limit_history, &files)?;
+            if commits.is_empty() {
+                return Err(Error::other(anyhow!(
+                    "no commits touch all of the given paths"
+                )));
+            }
+            commits
+        } else {
+            rev_list(&revisions, &limit_history, &files, &|_| ())?
+        };
+        if !exclude_authors.is_empty() {
+            let excluded = rev_list(&revisions, &limit_history, &files, &|cmd| {
+                cmd.arg("--basic-regexp");
+                for author in exclude_authors {
+                    cmd.arg(format!("--author={author}"));
+                }
+            })?
+            .into_iter()
+            .collect::<BTreeSet<_>>();
+            commits.retain(|commit| !excluded.contains(commit));
+            if commits.is_empty() {
+                return Err(Error::other(anyhow!(
+                    "all commits were excluded by their authors"
+                )));
+            }
+        }
+        (
+            Revisions::new(Vec::new(), commits.iter().map(|c| c.as_str()))?,
+            true,
+        )
+    } else {
+        (revisions, false)
+    };
+    Ok(HistorySelection {
+        merge_base,
+        revisions,
+        no_walk,
+        limits,
+        author: author.clone(),
+        max_count: *max_count,
+        files,
     })
-    .spawn_and_wait()
-    .map_err(Into::into)
-    .map_err(Error::other)
-    .and_then(Error::from_status)
 }
 
-pub fn list_branches_cmd(config: impl FnOnce(&mut Command) -> &mut Command) -> EasyCommand {
-    EasyCommand::new_with("git", |cmd| {
-        config(cmd.args(["branch", "--list", "--format=%(refname:short)"]))
+/// Commits of `revisions` that touch all of `files`, in the order that `git rev-list` lists them.
+///
+/// Git's pathspecs select commits that touch _any_ of their paths, so this runs `git rev-list`
+/// once for each path and intersects the results.
+fn commits_touching_all(
+    revisions: &Revisions,
+    limit_history: &dyn Fn(&mut Command),
+    files: &[&OsStr],
+) -> Result<Vec<String>> {
+    let mut commits = None::<Vec<String>>;
+    for file in files {
+        let touching = rev_list(revisions, limit_history, &[file], &|_| ())?;
+        commits = Some(match commits {
+            None => touching,
+            Some(commits) => {
+                let touching = touching.into_iter().collect::<BTreeSet<_>>();
+                commits
+                    .into_iter()
+                    .filter(|commit| touching.contains(commit))
+                    .collect()
+            }
+        });
+    }
+    Ok(commits.unwrap_or_default())
+}
+
+/// If `count` exceeds the `glimpse.warnCommitThreshold` config, ask on the terminal whether to
+/// continue showing that many commits, failing if not. A threshold of 0 disables this.
+fn confirm_commit_count(count: usize) -> Result<()> {
+    let threshold = match git_config("glimpse.warnCommitThreshold")? {
+        Some(threshold) => threshold
+            .parse::<usize>()
+            .with_context(|| {
+                format!("`glimpse.warnCommitThreshold` is not a number of commits: {threshold:?}")
+            })
+            .map_err(Error::other)?,
+        None => 1000,
+    };
+    if threshold == 0 || count <= threshold {
+        return Ok(());
+    }
+    clear_preview();
+    eprint!("This will show {count} commits. Continue? [y/N] ");
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .context("failed to read answer from terminal")
+        .map_err(Error::other)?;
+    match answer.trim() {
+        "y" | "Y" | "yes" => Ok(()),
+        _ => Err(Error::other(anyhow!(
+            "not showing {count} commits; pass `--yes` to skip this confirmation"
+        ))),
+    }
+}
+
+/// The number of commits shown by [`show_preview`].
+const PREVIEW_COMMITS: usize = 20;
+
+/// The number of terminal rows written by [`show_preview`] that have yet to be cleared by
+/// [`clear_preview`].
+static PREVIEW_ROWS: AtomicUsize = AtomicUsize::new(0);
+
+/// Write a graph of the most recent commits at `HEAD` to `stdout`, which is a terminal, for
+/// [`GraphOptions::progressive`].
+///
+/// Line wrapping is disabled while writing, so that each line takes exactly one row, and can be
+/// cleared later by [`clear_preview`].
+fn show_preview(options: &GraphOptions) -> Result<()> {
+    let color = match options.render.color {
+        ColorChoice::Auto => post_process::should_color()?,
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+    let format = options
+        .format
+        .as_deref()
+        .unwrap_or(default_format(options.committer_date));
+    let Output { stdout, status, .. } = EasyCommand::new_with("git", |cmd| {
+        cmd.args(["log", "--graph", "--no-show-signature"])
+            .arg(format!("--max-count={PREVIEW_COMMITS}"))
+            .arg(format!("--format={format}"))
+            .arg(if color {
+                "--color=always"
+            } else {
+                "--color=never"
+            })
+            .arg("HEAD")
     })
+    .output()
+    .map_err(Error::from_spawn)?;
+    if !status.success() {
+        log::debug!("failed to show preview, i.e., because `HEAD` is unborn; skipping it");
+        return Ok(());
+    }
+    let rows = stdout.iter().filter(|&&b| b == b'\n').count();
+    let mut preview = b"\x1b[?7l".to_vec();
+    preview.extend(stdout);
+    preview.extend(b"\x1b[?7h");
+    post_process::write_stdout(&preview)?;
+    PREVIEW_ROWS.store(rows, Ordering::Relaxed);
+    Ok(())
 }
 
-pub fn stdout_lines(mut cmd: EasyCommand) -> Result<Vec<String>> {
-    let output = cmd.output().map_err(Into::into).map_err(Error::other)?;
-    let Output {
-        stdout,
-        stderr,
-        status,
-    } = output;
+/// Clear what [`show_preview`] wrote, if anything, moving the cursor back to where it started.
+fn clear_preview() {
+    let rows = PREVIEW_ROWS.swap(0, Ordering::Relaxed);
+    if rows > 0 {
+        // There's nothing useful to do if this fails, since the graph would fail to write next.
+        let _ = post_process::write_stdout(format!("\x1b[{rows}F\x1b[J").as_bytes());
+    }
+}
 
-    let status_res = Error::from_status(status);
-    if status_res.is_err() {
-        io::copy(&mut Cursor::new(stderr), &mut io::stderr()).unwrap();
+/// The full object names of each of `commits` whose message has no `Signed-off-by` trailer.
+fn commits_without_signoff<'a, Os>(commits: Os) -> Result<Vec<String>>
+where
+    Os: IntoIterator<Item = &'a str>,
+{
+    let revisions = Revisions::new(Vec::new(), commits)?;
+    if revisions.revisions.is_empty() {
+        return Ok(Vec::new());
     }
-    status_res?;
+    let lines = stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            cmd.args([
+                "log",
+                "--no-walk=unsorted",
+                "--no-show-signature",
+                "--format=%H%x00%(trailers:key=Signed-off-by,valueonly,separator=%x2C)",
+            ]);
+            revisions.pass_to(cmd)
+        }),
+        true,
+    )?;
+    Ok(lines
+        .into_iter()
+        .filter_map(|line| {
+            let (commit, signoffs) = line.split_once('\0')?;
+            signoffs.trim().is_empty().then(|| commit.to_owned())
+        })
+        .collect())
+}
 
-    let stdout = String::from_utf8(stdout)
-        .context("`stdout` was not UTF-8 (!?)")
-        .map_err(Error::other)?;
-    Ok(stdout.lines().map(|line| line.trim().to_owned()).collect())
+/// The full object names of each of `commits` that is a merge of divergent history, i.e., none of
+/// whose parents is an ancestor of another.
+fn true_merges<'a, Os>(commits: Os) -> Result<Vec<String>>
+where
+    Os: IntoIterator<Item = &'a str>,
+{
+    let revisions = Revisions::new(Vec::new(), commits)?;
+    if revisions.revisions.is_empty() {
+        return Ok(Vec::new());
+    }
+    let lines = stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            cmd.args([
+                "log",
+                "--no-walk=unsorted",
+                "--no-show-signature",
+                "--min-parents=2",
+                "--format=%H %P",
+            ]);
+            revisions.pass_to(cmd)
+        }),
+        true,
+    )?;
+    let mut merges = Vec::new();
+    for line in lines {
+        let mut fields = line.split(' ');
+        let Some(commit) = fields.next() else {
+            continue;
+        };
+        let parents = fields.collect::<Vec<_>>();
+        // Parents that are ancestors of others are omitted, like for a fast-forwardable merge.
+        let independent = stdout_lines(
+            EasyCommand::new_with("git", |cmd| {
+                cmd.args(["merge-base", "--independent", "--end-of-options"])
+                    .args(&parents)
+            }),
+            true,
+        )?;
+        if independent.len() == parents.len() {
+            merges.push(commit.to_owned());
+        }
+    }
+    Ok(merges)
 }
 
-pub fn git_config(path: &str) -> Result<Option<String>> {
-    let mut cmd = EasyCommand::new_with("git", |cmd| cmd.arg("config").arg(path));
-    let output = cmd.output().map_err(Into::into).map_err(Error::other)?;
-    let Output {
-        stdout,
-        stderr,
-        status,
-    } = output;
+/// Those of `commits` that aren't reachable from any remote-tracking branch, in the order that
+/// `git rev-list` lists them. History below `merge_base`, if specified, isn't walked.
+fn unpushed_commits<'a, Os>(commits: Os, merge_base: Option<&str>) -> Result<Vec<String>>
+where
+    Os: IntoIterator<Item = &'a str>,
+{
+    let commits = commits.into_iter().collect::<BTreeSet<_>>();
+    if commits.is_empty() {
+        return Ok(Vec::new());
+    }
+    let remotes = ref_lines(EasyCommand::new_with("git", |cmd| {
+        cmd.args(["for-each-ref", "--format=%(objectname)", "refs/remotes/"])
+    }))?;
+    if remotes.is_empty() {
+        log::warn!("there are no remote-tracking branches, so every commit is unpushed");
+    }
+    let bottoms = remotes
+        .iter()
+        .map(|remote| format!("^{remote}"))
+        .chain(merge_base.map(|merge_base| format!("^{merge_base}^@")))
+        .collect::<Vec<_>>();
+    let revisions = Revisions::new(bottoms, commits.iter().copied())?;
+    let unpushed = stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            cmd.arg("rev-list");
+            revisions.pass_to(cmd)
+        }),
+        true,
+    )?;
+    Ok(unpushed
+        .into_iter()
+        .filter(|commit| commits.contains(commit.as_str()))
+        .collect())
+}
 
-    match status.code() {
-        Some(0) => (),
-        Some(1) => return Ok(None),
-        _ => {
-            io::copy(&mut Cursor::new(stderr), &mut io::stderr()).unwrap();
-            return Err(Error::from_status(status).unwrap_err());
+/// Whether `commits` form a single line of history, i.e., none of them has more than one parent or
+/// child among them, and only one has none of its parents among them.
+fn is_linear<'a, Os>(commits: Os) -> Result<bool>
+where
+    Os: IntoIterator<Item = &'a str>,
+{
+    let revisions = Revisions::new(Vec::new(), commits)?;
+    let shown = revisions
+        .revisions
+        .iter()
+        .map(String::as_str)
+        .collect::<BTreeSet<_>>();
+    if shown.is_empty() {
+        return Ok(true);
+    }
+    let lines = stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            cmd.args([
+                "log",
+                "--no-walk=unsorted",
+                "--no-show-signature",
+                "--format=%P",
+            ]);
+            revisions.pass_to(cmd)
+        }),
+        true,
+    )?;
+    Ok(is_linear_by_parents(
+        &shown,
+        lines.iter().map(String::as_str),
+    ))
+}
+
+/// The check of [`is_linear`], given the space-separated `parents` of each of the `shown` commits.
+fn is_linear_by_parents<'a>(
+    shown: &BTreeSet<&str>,
+    parents: impl IntoIterator<Item = &'a str>,
+) -> bool {
+    let mut roots = 0;
+    let mut children = BTreeSet::new();
+    for line in parents {
+        let parents = line
+            .split_whitespace()
+            .filter(|parent| shown.contains(parent))
+            .collect::<Vec<_>>();
+        match parents[..] {
+            [] => roots += 1,
+            [parent] if children.insert(parent) => (),
+            _ => return false,
         }
-    };
+    }
+    roots <= 1
+}
 
-    let stdout = String::from_utf8(stdout)
-        .context("`stdout` was not UTF-8 (!?)")
-        .map_err(Error::other)?;
+/// Pair each `fixup!`, `squash!`, or `amend!` commit of `commits` (newest first) with the
+/// abbreviated object name of the older commit it targets, like `git rebase --autosquash` would.
+///
+/// Like Git, this matches the rest of the subject against whole subjects first, then against
+/// object names, and then against the beginnings of subjects. Fixups whose targets aren't among
+/// `commits` are skipped with a warning.
+fn fixup_targets<'a, Os>(commits: Os) -> Result<Vec<(String, String)>>
+where
+    Os: IntoIterator<Item = &'a str>,
+{
+    let revisions = Revisions::new(Vec::new(), commits)?;
+    if revisions.revisions.is_empty() {
+        return Ok(Vec::new());
+    }
+    let lines = stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            cmd.args([
+                "log",
+                "--no-walk=unsorted",
+                "--no-show-signature",
+                "--encoding=UTF-8",
+            ])
+            .arg("--format=%H%x1f%h%x1f%s");
+            revisions.pass_to(cmd)
+        }),
+        true,
+    )?;
+    let commits = lines
+        .iter()
+        .map(|line| {
+            let mut fields = line.splitn(3, '\x1f');
+            let mut field = || fields.next().unwrap_or_default();
+            (field(), field(), field())
+        })
+        .collect::<Vec<_>>();
+    Ok(autosquash_targets(&commits))
+}
 
-    let mut lines = stdout.lines().map(|line| line.trim().to_owned());
-    log::trace!("`stdout` of {cmd}: {lines:?}");
+/// The matching of [`fixup_targets`], given the object name, abbreviated object name, and subject
+/// of each of its commits, newest first.
+fn autosquash_targets(commits: &[(&str, &str, &str)]) -> Vec<(String, String)> {
+    const PREFIXES: [&str; 3] = ["fixup! ", "squash! ", "amend! "];
 
-    let first_line = lines.next();
-    assert!(
-        lines.next().is_none(),
-        "{cmd} returned more than a single line of output"
+    let mut targets = Vec::new();
+    for (idx, (hash, _abbrev, subject)) in commits.iter().enumerate() {
+        let mut rest = *subject;
+        while let Some(stripped) = PREFIXES.iter().find_map(|prefix| rest.strip_prefix(prefix)) {
+            rest = stripped;
+        }
+        if rest.len() == subject.len() {
+            continue;
+        }
+        // Only older commits can be targeted, and `git rebase` prefers the oldest match.
+        let older = &commits[idx + 1..];
+        let target = older
+            .iter()
+            .rev()
+            .find(|(_, _, subject)| *subject == rest)
+            .or_else(|| {
+                (!rest.is_empty())
+                    .then(|| {
+                        older
+                            .iter()
+                            .rev()
+                            .find(|(hash, _, _)| hash.starts_with(rest))
+                    })
+                    .flatten()
+            })
+            .or_else(|| {
+                older
+                    .iter()
+                    .rev()
+                    .find(|(_, _, subject)| subject.starts_with(rest))
+            });
+        match target {
+            Some((_, abbrev, _)) => targets.push(((*hash).to_owned(), (*abbrev).to_owned())),
+            None => log::warn!("couldn't find the target of fixup {hash} among shown commits"),
+        }
+    }
+    targets
+}
+
+/// Commits of `revisions` that touch any of `files` (or all commits, if there are none), as listed
+/// by `git rev-list` further configured with `cmd_config`.
+fn rev_list(
+    revisions: &Revisions,
+    limit_history: &dyn Fn(&mut Command),
+    files: &[&OsStr],
+    cmd_config: &dyn Fn(&mut Command),
+) -> Result<Vec<String>> {
+    stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            cmd.arg("rev-list");
+            limit_history(cmd);
+            cmd_config(cmd);
+            revisions.pass_to(cmd).arg("--").args(files)
+        }),
+        true,
+    )
+}
+
+/// The format to use when none is specified or configured, before [`auto_decorate`] and such.
+fn default_format(committer_date: bool) -> &'static str {
+    if committer_date {
+        COMMITTER_DATE_FORMAT
+    } else {
+        DEFAULT_FORMAT
+    }
+}
+
+/// Handle custom formats that omit decorations, which would otherwise hide the refs that commits
+/// were selected by. If the `glimpse.autoDecorate` config is set, `%d` is appended to such
+/// formats. If it's unset, a warning is emitted instead.
+fn auto_decorate(format: String) -> Result<String> {
+    let decorated = ["%d", "%D", "%(decorate"]
+        .iter()
+        .any(|placeholder| format.contains(placeholder));
+    if decorated {
+        return Ok(format);
+    }
+    let Some(appended) = post_process::append_to_format(&format, "%C(auto)%d") else {
+        return Ok(format);
+    };
+    match git_config_bool("glimpse.autoDecorate")? {
+        Some(true) => {
+            log::trace!("appending `%d` to format without decorations");
+            Ok(appended)
+        }
+        Some(false) => Ok(format),
+        None => {
+            log::warn!(
+                "format {format:?} has no decorations (i.e., `%d`), so refs won't be shown; set \
+                `glimpse.autoDecorate` to `true` to append them automatically, or to `false` to \
+                silence this warning"
+            );
+            Ok(format)
+        }
+    }
+}
+
+/// The time `age` ago, in seconds since the Unix epoch.
+fn cutoff(age: Duration) -> i64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    i64::try_from(now.saturating_sub(age).as_secs()).unwrap_or(i64::MAX)
+}
+
+/// Negated revisions that prune history more than `max_depth` commits from each of
+/// `object_names`, in addition to that of the negated revisions `bottoms`.
+///
+/// Commits are counted in the same order as `git rev-list --max-count`, so this approximates
+/// depth, rather than measuring it exactly. Since negated revisions take precedence, commits of
+/// other object names that are ancestors of pruned history are pruned, too.
+fn depth_bottoms<'a, Os>(
+    max_depth: usize,
+    bottoms: Vec<String>,
+    object_names: Os,
+) -> Result<Vec<String>>
+where
+    Os: IntoIterator<Item = &'a str>,
+{
+    let mut parents_by_commit = ParentsByCommit::new();
+    for object_name in object_names {
+        let lines = stdout_lines(
+            EasyCommand::new_with("git", |cmd| {
+                cmd.args(["rev-list", "--parents"])
+                    .arg(format!("--max-count={max_depth}"))
+                    .arg("--end-of-options")
+                    .args(&bottoms)
+                    .arg(object_name)
+            }),
+            true,
+        )?;
+        parents_by_commit.extend(lines);
+    }
+    let boundary = parents_by_commit.boundary();
+    log::debug!(
+        "pruning history at {} commits deeper than {max_depth}",
+        boundary.len()
     );
-    Ok(first_line)
+    Ok(bottoms
+        .into_iter()
+        .chain(boundary.into_iter().map(|parent| format!("^{parent}")))
+        .collect())
+}
+
+/// A set of commits and their parents, from `git rev-list --parents` output.
+struct ParentsByCommit(BTreeMap<String, Vec<String>>);
+
+impl ParentsByCommit {
+    fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    fn extend(&mut self, lines: impl IntoIterator<Item = String>) {
+        for line in lines {
+            let mut oids = line.split(' ').map(ToOwned::to_owned);
+            if let Some(commit) = oids.next() {
+                self.0.entry(commit).or_insert_with(|| oids.collect());
+            }
+        }
+    }
+
+    /// Parents of commits in this set that aren't themselves in it.
+    fn boundary(&self) -> BTreeSet<&str> {
+        self.0
+            .values()
+            .flatten()
+            .filter(|parent| !self.0.contains_key(*parent))
+            .map(|parent| parent.as_str())
+            .collect()
+    }
+}
+
+/// Revision arguments for `git log`, which are passed via `--stdin` when they're too long to pass
+/// as command-line arguments.
+struct Revisions {
+    revisions: Vec<String>,
+    /// The file from which `revisions` are read via `--stdin`, if any. Removed when dropped.
+    file: Option<PathBuf>,
+}
+
+impl Revisions {
+    /// The total length of revisions above which they are passed via `--stdin`. This stays well
+    /// within OS limits on command-line length, the smallest of which is Windows' 32 KiB.
+    const STDIN_THRESHOLD: usize = 16 * 1024;
+
+    /// Revisions for the history of `object_names`, minus that of the negated revisions
+    /// `bottoms`.
+    fn new<'a, Os>(bottoms: Vec<String>, object_names: Os) -> Result<Self>
+    where
+        Os: IntoIterator<Item = &'a str>,
+    {
+        let revisions = bottoms
+            .into_iter()
+            .chain(object_names.into_iter().map(ToOwned::to_owned))
+            .collect::<Vec<_>>();
+        if args_len(revisions.iter().map(|r| r.as_str())) <= Self::STDIN_THRESHOLD {
+            return Ok(Self {
+                revisions,
+                file: None,
+            });
+        }
+
+        // Temporary file names are predictable, so never open one that already exists, which (say)
+        // another user may have planted as a symlink elsewhere.
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let (path, mut file) = loop {
+            let path = env::temp_dir().join(format!(
+                "git-glimpse-{}-{}.revs",
+                process::id(),
+                NEXT_ID.fetch_add(1, Ordering::Relaxed)
+            ));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => break (path, file),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(Error::other(anyhow!(e).context(format!(
+                        "failed to create {} for revisions",
+                        path.display()
+                    ))))
+                }
+            }
+        };
+        log::debug!(
+            "passing {} revisions via `--stdin` from {}",
+            revisions.len(),
+            path.display()
+        );
+        let written = file.write_all((revisions.join("\n") + "\n").as_bytes());
+        drop(file);
+        if let Err(e) = written {
+            let _ = fs::remove_file(&path);
+            return Err(Error::other(anyhow!(e).context(format!(
+                "failed to write revisions to {}",
+                path.display()
+            ))));
+        }
+        Ok(Self {
+            revisions,
+            file: Some(path),
+        })
+    }
+
+    fn pass_to<'c>(&self, cmd: &'c mut Command) -> &'c mut Command {
+        let Self { revisions, file } = self;
+        match file.as_deref().map(File::open) {
+            Some(Ok(file)) => cmd.arg("--stdin").stdin(file),
+            Some(Err(e)) => {
+                log::warn!("failed to open revisions file, passing them as arguments: {e}");
+                cmd.arg("--end-of-options").args(revisions)
+            }
+            None => cmd.arg("--end-of-options").args(revisions),
+        }
+    }
+}
+
+impl Drop for Revisions {
+    fn drop(&mut self) {
+        if let Some(path) = &self.file {
+            if let Err(e) = fs::remove_file(path) {
+                log::warn!("failed to remove {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// The total length of `args` on a command line.
+fn args_len<'a>(args: impl IntoIterator<Item = &'a str>) -> usize {
+    args.into_iter().map(|arg| arg.len() + 1).sum()
+}
+
+/// Resolve `rev` to the full object name of a commit.
+fn rev_parse(rev: &str) -> Result<String> {
+    let mut output = stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            cmd.args(["rev-parse", "--verify", "--end-of-options"])
+                .arg(format!("{rev}^{{commit}}"))
+        }),
+        true,
+    )?;
+    output
+        .pop()
+        .ok_or_else(|| Error::other(anyhow!("failed to resolve {rev:?} to a commit")))
+}
+
+/// Drop those of `object_names` that don't resolve to commits with a warning, i.e., refs deleted
+/// since they were listed by another tool. Fails if none of them resolve.
+pub fn drop_missing(object_names: Vec<String>) -> Result<Vec<String>> {
+    if object_names.is_empty() {
+        return Ok(object_names);
+    }
+    let mut resolved = Vec::with_capacity(object_names.len());
+    for object_name in object_names {
+        let Output { status, .. } = EasyCommand::new_with("git", |cmd| {
+            cmd.args(["rev-parse", "--verify", "--quiet", "--end-of-options"])
+                .arg(format!("{object_name}^{{commit}}"))
+        })
+        .output()
+        .map_err(Error::from_spawn)?;
+        if status.success() {
+            resolved.push(object_name);
+        } else {
+            log::warn!("ignoring {object_name:?}, which doesn't resolve to a commit");
+        }
+    }
+    if resolved.is_empty() {
+        return Err(Error::other(anyhow!(
+            "none of the selected refs resolve to commits"
+        )));
+    }
+    Ok(resolved)
+}
+
+/// Find the merge base of all of `object_names`, or `None`, with a warning, if they have no
+/// common ancestor, i.e., when some are from unrelated histories.
+fn merge_base<'a, Os>(object_names: Os) -> Result<Option<String>>
+where
+    Os: IntoIterator<Item = &'a str>,
+{
+    let object_names = object_names.into_iter().collect::<Vec<_>>();
+    // `git merge-base` can't read revisions from `stdin`, so we split large sets of them across
+    // multiple invocations, each including the merge base of the previous ones.
+    let chunk_size = if args_len(object_names.iter().copied()) > Revisions::STDIN_THRESHOLD {
+        256
+    } else {
+        object_names.len().max(1)
+    };
+    let mut merge_base = None::<String>;
+    for chunk in object_names.chunks(chunk_size) {
+        let output = EasyCommand::new_with("git", |cmd| {
+            cmd.args(["merge-base", "--octopus", "--end-of-options"])
+                .args(&merge_base)
+                .args(chunk)
+        })
+        .output()
+        .map_err(Error::from_spawn)?;
+        // Git exits with 1, printing nothing, when there's no common ancestor.
+        let no_common_ancestor = output.status.code() == Some(1) && output.stdout.is_empty();
+        let mut output = match lines_of(output, true) {
+            Err(Error::SubprocessFailedWithExplanation { .. }) if is_shallow_repository()? => {
+                return Err(Error::ShallowHistory)
+            }
+            _ if no_common_ancestor => Vec::new(),
+            output => output?,
+        };
+        if output.is_empty() {
+            log::warn!(
+                "selected commits have no common ancestor, so history isn't cut off at a merge \
+                base"
+            );
+            return Ok(None);
+        }
+        if output.len() != 1 {
+            return Err(Error::other(anyhow!(
+                "expected a single line of output, but got {}; \
+                output: {output:#?}",
+                output.len()
+            )));
+        }
+        merge_base = output.pop();
+    }
+    merge_base
+        .map(Some)
+        .ok_or_else(|| Error::other(anyhow!("no object names to find a merge base of")))
+}
+
+/// Negated revisions that cut history off below `merge_base`, i.e., its parents. There are none
+/// for root commits, below which there's no history to cut off.
+fn merge_base_bottoms(merge_base: &str) -> Result<Vec<String>> {
+    // Unlike `git log`, `git rev-list` never interleaves signatures (see `log.showSignature`).
+    Ok(stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            cmd.args(["rev-list", "--no-walk", "--parents", "--end-of-options"])
+                .arg(merge_base)
+        }),
+        true,
+    )?
+    .iter()
+    .flat_map(|line| line.split_whitespace().skip(1))
+    .map(|parent| format!("^{parent}"))
+    .collect())
+}
+
+/// Whether the current repository is a shallow clone, whose history is truncated.
+fn is_shallow_repository() -> Result<bool> {
+    let output = stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            cmd.args(["rev-parse", "--is-shallow-repository"])
+        }),
+        true,
+    )?;
+    Ok(output.first().is_some_and(|line| line == "true"))
+}
+
+/// Metadata for a single commit, as enumerated by [`stack_commits`] and [`graph_commits`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CommitInfo {
+    /// The full object name of this commit.
+    pub hash: String,
+    /// The full object names of this commit's parents, in order.
+    pub parents: Vec<String>,
+    pub author_name: String,
+    pub author_email: String,
+    /// The author date, in strict ISO 8601 format.
+    pub author_date: String,
+    /// The committer date, in strict ISO 8601 format.
+    pub committer_date: String,
+    pub subject: String,
+    /// Ref names pointing at this commit, as displayed by `git log --decorate` (i.e.,
+    /// `HEAD -> main`, `origin/main`, `tag: v1.0.0`).
+    pub decorations: Vec<String>,
+}
+
+impl CommitInfo {
+    const FIELD_SEP: char = '\x1f';
+    const FORMAT: &'static str = "%H%x1f%P%x1f%an%x1f%ae%x1f%aI%x1f%cI%x1f%D%x1f%s";
+
+    fn parse(line: &str) -> Result<Self> {
+        let mut fields = line.splitn(8, Self::FIELD_SEP);
+        let mut next_field = || {
+            fields
+                .next()
+                .ok_or_else(|| anyhow!("missing field(s) in commit line {line:?}"))
+                .map_err(Error::other)
+        };
+        let hash = next_field()?.to_owned();
+        let parents = next_field()?
+            .split_whitespace()
+            .map(ToOwned::to_owned)
+            .collect();
+        let author_name = next_field()?.to_owned();
+        let author_email = next_field()?.to_owned();
+        let author_date = next_field()?.to_owned();
+        let committer_date = next_field()?.to_owned();
+        let decorations = next_field()?
+            .split(", ")
+            .filter(|d| !d.is_empty())
+            .map(ToOwned::to_owned)
+            .collect();
+        let subject = next_field()?.to_owned();
+        Ok(Self {
+            hash,
+            parents,
+            author_name,
+            author_email,
+            author_date,
+            committer_date,
+            subject,
+            decorations,
+        })
+    }
+
+    /// The names of the refs in [`Self::decorations`], without the `HEAD -> ` and `tag: `
+    /// prefixes that Git displays, like `HEAD`, `main`, and `v1.0.0`.
+    pub fn ref_names(&self) -> Vec<String> {
+        self.decorations
+            .iter()
+            .flat_map(|decoration| match decoration.strip_prefix("HEAD -> ") {
+                Some(branch) => vec!["HEAD", branch],
+                None => vec![decoration.strip_prefix("tag: ").unwrap_or(decoration)],
+            })
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+}
+
+/// A column of [`show_tsv`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum TsvField {
+    /// The full object name of the commit.
+    Hash,
+    /// The full object names of the commit's parents, separated by spaces.
+    Parents,
+    Author,
+    AuthorEmail,
+    /// The author date, in strict ISO 8601 format.
+    Date,
+    /// The committer date, in strict ISO 8601 format.
+    CommitterDate,
+    /// Ref names pointing at the commit, separated by commas.
+    Decorations,
+    Subject,
+}
+
+impl TsvField {
+    /// The columns of [`show_tsv`] when none are specified.
+    pub const DEFAULTS: [Self; 4] = [Self::Hash, Self::Author, Self::Date, Self::Subject];
+}
+
+/// Print the commits that [`show_graph`] would display for `object_names` and `files` with
+/// `options` as tab-separated values of `fields`, with a header row, i.e., for pasting into a
+/// spreadsheet.
+///
+/// Commits are listed with `--date-order`, so rows are ordered the same way across runs, even
+/// for histories with many branches. Tabs and line breaks in values are replaced with spaces.
+pub fn show_tsv<'o, 'f, Os, Fs>(
+    options: &GraphOptions,
+    object_names: Os,
+    files: Fs,
+    fields: &[TsvField],
+) -> Result<()>
+where
+    Os: IntoIterator<Item = &'o str> + Clone,
+    Fs: IntoIterator<Item = &'f OsStr>,
+{
+    let commits = log_commits_in_order("--date-order", options, object_names, files)?;
+    let header = fields
+        .iter()
+        .map(|field| field.to_possible_value().unwrap().get_name().to_owned())
+        .collect::<Vec<_>>();
+    println!("{}", header.join("\t"));
+    for commit in &commits {
+        let CommitInfo {
+            hash,
+            parents,
+            author_name,
+            author_email,
+            author_date,
+            committer_date,
+            subject,
+            decorations,
+        } = commit;
+        let row = fields
+            .iter()
+            .map(|field| {
+                let value = match field {
+                    TsvField::Hash => hash.clone(),
+                    TsvField::Parents => parents.join(" "),
+                    TsvField::Author => author_name.clone(),
+                    TsvField::AuthorEmail => author_email.clone(),
+                    TsvField::Date => author_date.clone(),
+                    TsvField::CommitterDate => committer_date.clone(),
+                    TsvField::Decorations => decorations.join(", "),
+                    TsvField::Subject => subject.clone(),
+                };
+                value.replace(['\t', '\n', '\r'], " ")
+            })
+            .collect::<Vec<_>>();
+        println!("{}", row.join("\t"));
+    }
+    Ok(())
+}
+
+/// Enumerate the commits that [`show_graph`] would display for `object_names` and `files` with
+/// `options`, in topological order, without rendering anything.
+fn log_commits<'o, 'f, Os, Fs>(
+    options: &GraphOptions,
+    object_names: Os,
+    files: Fs,
+) -> Result<Vec<CommitInfo>>
+where
+    Os: IntoIterator<Item = &'o str> + Clone,
+    Fs: IntoIterator<Item = &'f OsStr>,
+{
+    log_commits_in_order("--topo-order", options, object_names, files)
+}
+
+/// Like [`log_commits`], but in the `git log` commit `order` specified (e.g., `--date-order`).
+fn log_commits_in_order<'o, 'f, Os, Fs>(
+    order: &str,
+    options: &GraphOptions,
+    object_names: Os,
+    files: Fs,
+) -> Result<Vec<CommitInfo>>
+where
+    Os: IntoIterator<Item = &'o str> + Clone,
+    Fs: IntoIterator<Item = &'f OsStr>,
+{
+    let selection = select_history(options, object_names, files)?;
+    commits_with(|cmd| selection.pass_to(cmd.arg(order)))
+}
+
+/// Print a SHA-256 digest of the commits that [`show_graph`] would display for `object_names` and
+/// `files` with `options`, i.e., for scripts to detect when a selection changes.
+///
+/// Only the object names of commits and their parents are digested, sorted, so that the digest
+/// doesn't depend on how the graph would be rendered.
+pub fn show_digest<'o, 'f, Os, Fs>(
+    options: &GraphOptions,
+    object_names: Os,
+    files: Fs,
+) -> Result<()>
+where
+    Os: IntoIterator<Item = &'o str> + Clone,
+    Fs: IntoIterator<Item = &'f OsStr>,
+{
+    let commits = log_commits(options, object_names, files)?
+        .into_iter()
+        .map(|CommitInfo { hash, parents, .. }| (hash, parents))
+        .collect::<BTreeSet<_>>();
+    let mut hasher = Sha256::new();
+    for (hash, parents) in commits {
+        hasher.update(hash);
+        for parent in parents {
+            hasher.update(" ");
+            hasher.update(parent);
+        }
+        hasher.update("\n");
+    }
+    let digest = hasher.finalize();
+    println!(
+        "{}",
+        digest
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>()
+    );
+    Ok(())
+}
+
+/// How [`show_graph`]'s selection of commits is printed. All but [`Self::Text`] ignore display
+/// options.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum OutputMode {
+    /// A graph rendered by `git log --graph`.
+    #[default]
+    Text,
+    /// A JSON array of commits, for scripts (see [`show_graph_json`]).
+    Json,
+    /// A Graphviz DOT digraph, i.e., for rendering with `dot -Tsvg` (see [`show_dot`]).
+    Dot,
+    /// A Mermaid `gitGraph` diagram, i.e., for embedding in Markdown (see [`show_mermaid`]).
+    Mermaid,
+    /// Tab-separated values with a header row, i.e., for pasting into a spreadsheet (see
+    /// [`show_tsv`]).
+    Tsv,
+    /// A SHA-256 digest of the selected commits and their parents, i.e., for scripts to detect
+    /// when a selection changes (see [`show_digest`]).
+    Digest,
+}
+
+/// Print the commits that [`show_graph`] would display for `object_names` and `files` with
+/// `options` as a JSON array, newest first, i.e., for scripts that would otherwise parse rendered
+/// graphs.
+///
+/// Each commit is an object with its `hash`, `parents` (all of them, even those not displayed),
+/// `refs` decorating it (see [`CommitInfo::ref_names`]), `subject`, `author` name, and `committed_at` date in strict ISO 8601
+/// format. Arrays are empty, rather than `null`, when there's nothing to list.
+pub fn show_graph_json<'o, 'f, Os, Fs>(
+    options: &GraphOptions,
+    object_names: Os,
+    files: Fs,
+) -> Result<()>
+where
+    Os: IntoIterator<Item = &'o str> + Clone,
+    Fs: IntoIterator<Item = &'f OsStr>,
+{
+    fn string(s: &str) -> String {
+        let mut escaped = String::with_capacity(s.len() + 2);
+        escaped.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => escaped.push_str("\\r"),
+                '\t' => escaped.push_str("\\t"),
+                c if c < ' ' => escaped.push_str(&format!("\\u{:04x}", u32::from(c))),
+                c => escaped.push(c),
+            }
+        }
+        escaped.push('"');
+        escaped
+    }
+
+    fn array(items: &[String]) -> String {
+        let items = items.iter().map(|item| string(item)).collect::<Vec<_>>();
+        format!("[{}]", items.join(", "))
+    }
+
+    let commits = log_commits(options, object_names, files)?;
+    let objects = commits
+        .iter()
+        .map(
+            |commit @ CommitInfo {
+                 hash,
+                 parents,
+                 author_name,
+                 committer_date,
+                 subject,
+                 ..
+             }| {
+                format!(
+                    "  {{\"hash\": {}, \"parents\": {}, \"refs\": {}, \"subject\": {}, \
+                    \"author\": {}, \"committed_at\": {}}}",
+                    string(hash),
+                    array(parents),
+                    array(&commit.ref_names()),
+                    string(subject),
+                    string(author_name),
+                    string(committer_date),
+                )
+            },
+        )
+        .collect::<Vec<_>>();
+    if objects.is_empty() {
+        println!("[]");
+    } else {
+        println!("[\n{}\n]", objects.join(",\n"));
+    }
+    Ok(())
+}
+
+/// Print the commits that [`show_graph`] would display for `object_names` and `files` with
+/// `options` as a Graphviz DOT digraph, i.e., for rendering with `dot -Tsvg`.
+///
+/// Commits are labeled with their abbreviated object name, decorations, and subject, with edges
+/// to those of their parents that are also displayed.
+pub fn show_dot<'o, 'f, Os, Fs>(options: &GraphOptions, object_names: Os, files: Fs) -> Result<()>
+where
+    Os: IntoIterator<Item = &'o str> + Clone,
+    Fs: IntoIterator<Item = &'f OsStr>,
+{
+    fn escape(s: &str) -> String {
+        s.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+
+    let commits = log_commits(options, object_names, files)?;
+    let shown = commits
+        .iter()
+        .map(|commit| commit.hash.as_str())
+        .collect::<BTreeSet<_>>();
+    println!("digraph commits {{");
+    println!("    node [shape=box, fontname=monospace];");
+    for CommitInfo {
+        hash,
+        decorations,
+        subject,
+        ..
+    } in &commits
+    {
+        let mut name = hash[..hash.len().min(7)].to_owned();
+        if !decorations.is_empty() {
+            name += &format!(" ({})", decorations.join(", "));
+        }
+        println!(
+            "    \"{hash}\" [label=\"{}\\n{}\"];",
+            escape(&name),
+            escape(subject)
+        );
+    }
+    for CommitInfo { hash, parents, .. } in &commits {
+        for parent in parents
+            .iter()
+            .filter(|parent| shown.contains(parent.as_str()))
+        {
+            println!("    \"{hash}\" -> \"{parent}\";");
+        }
+    }
+    println!("}}");
+    Ok(())
+}
+
+/// Print the commits that [`show_graph`] would display for `object_names` and `files` with
+/// `options` as a Mermaid `gitGraph` diagram, i.e., for embedding in Markdown.
+///
+/// Each line of first parents is drawn as a Mermaid branch, named after a branch pointing at its
+/// tip, if any. Mermaid can only draw branches forking from a single root, and merges of the tips
+/// of branches, so a warning is emitted for history that can't be drawn faithfully.
+pub fn show_mermaid<'o, 'f, Os, Fs>(
+    options: &GraphOptions,
+    object_names: Os,
+    files: Fs,
+) -> Result<()>
+where
+    Os: IntoIterator<Item = &'o str> + Clone,
+    Fs: IntoIterator<Item = &'f OsStr>,
+{
+    let mut commits = log_commits(options, object_names, files)?;
+    commits.reverse();
+    let index = commits
+        .iter()
+        .enumerate()
+        .map(|(idx, commit)| (commit.hash.as_str(), idx))
+        .collect::<BTreeMap<_, _>>();
+    let parents = commits
+        .iter()
+        .map(|commit| {
+            commit
+                .parents
+                .iter()
+                .filter_map(|parent| index.get(parent.as_str()).copied())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let (lanes, tips, forks) = mermaid_lanes(&parents);
+    let mut names = Vec::<String>::with_capacity(tips.len());
+    for (lane, &tip) in tips.iter().enumerate() {
+        let name = commits[tip]
+            .decorations
+            .iter()
+            .map(|decoration| decoration.strip_prefix("HEAD -> ").unwrap_or(decoration))
+            .find(|decoration| *decoration != "HEAD" && !decoration.starts_with("tag: "))
+            .map_or_else(|| format!("lane-{lane}"), ToOwned::to_owned);
+        let name = if names.contains(&name) {
+            format!("{name}-{lane}")
+        } else {
+            name
+        };
+        names.push(name);
+    }
+
+    if let Some(name) = names.first().filter(|name| *name != "main") {
+        println!("%%{{init: {{ 'gitGraph': {{ 'mainBranchName': '{name}' }} }} }}%%");
+    }
+    println!("gitGraph");
+    let mut current = 0;
+    let mut created = vec![false; tips.len()];
+    let mut emitted_tips = vec![None; tips.len()];
+    if let Some(created) = created.first_mut() {
+        *created = true;
+    }
+    for (idx, commit) in commits.iter().enumerate() {
+        let lane = lanes[idx];
+        let id = &commit.hash[..commit.hash.len().min(7)];
+        if !created[lane] {
+            log::warn!(
+                "{id} has no parents among the commits shown, but Mermaid can't draw separate \
+                roots; drawing it as a fork of {:?}",
+                names[current]
+            );
+            println!("    branch {}", names[lane]);
+            created[lane] = true;
+        } else if current != lane {
+            println!("    checkout {}", names[lane]);
+        }
+        current = lane;
+        let tag = commit
+            .decorations
+            .iter()
+            .find_map(|decoration| decoration.strip_prefix("tag: "))
+            .map(|tag| format!(" tag: \"{tag}\""))
+            .unwrap_or_default();
+        match parents[idx][..] {
+            [] | [_] => println!("    commit id: \"{id}\"{tag}"),
+            [_, merged, ..] if lanes[merged] == lane => {
+                log::warn!(
+                    "{id} merges a commit of its own first-parent line, which Mermaid can't draw; \
+                    drawing it as a regular commit"
+                );
+                println!("    commit id: \"{id}\"{tag}");
+            }
+            [_, merged, ref rest @ ..] => {
+                if !rest.is_empty() {
+                    log::warn!(
+                        "{id} is an octopus merge, which Mermaid can't draw; only drawing its \
+                        first merged parent"
+                    );
+                }
+                if emitted_tips[lanes[merged]] != Some(merged) {
+                    log::warn!(
+                        "{id} merges a commit that isn't the tip of {:?}, which Mermaid can't \
+                        draw; drawing a merge of its tip instead",
+                        names[lanes[merged]]
+                    );
+                }
+                println!("    merge {} id: \"{id}\"{tag}", names[lanes[merged]]);
+            }
+        }
+        emitted_tips[lane] = Some(idx);
+        for &fork in forks.get(&idx).into_iter().flatten() {
+            println!("    branch {}", names[fork]);
+            created[fork] = true;
+            current = fork;
+        }
+    }
+    Ok(())
+}
+
+/// Assign each of the commits of [`show_mermaid`] to a lane, i.e., a Mermaid branch, given the
+/// indices of the `parents` of each, which precede it.
+///
+/// Each commit continues the lane of its first parent (among those shown), unless another child
+/// already has. Returns the lane of each commit, the tip of each lane, and the lanes forking from
+/// each commit.
+fn mermaid_lanes(parents: &[Vec<usize>]) -> (Vec<usize>, Vec<usize>, BTreeMap<usize, Vec<usize>>) {
+    let mut lanes = Vec::with_capacity(parents.len());
+    let mut tips = Vec::<usize>::new();
+    let mut forks = BTreeMap::<usize, Vec<usize>>::new();
+    let mut continued = BTreeSet::new();
+    for (idx, parents) in parents.iter().enumerate() {
+        let lane = match parents.first().copied() {
+            Some(parent) if continued.insert(parent) => lanes[parent],
+            parent => {
+                let lane = tips.len();
+                tips.push(idx);
+                if let Some(parent) = parent {
+                    forks.entry(parent).or_default().push(lane);
+                }
+                lane
+            }
+        };
+        tips[lane] = idx;
+        lanes.push(lane);
+    }
+    (lanes, tips, forks)
+}
+
+/// Enumerate commits via `git log --topo-order`, with revisions and other arguments specified
+/// by `config`. Revisions should follow an `--end-of-options` argument, so they can't be
+/// misinterpreted as options.
+fn commits_with(config: impl FnOnce(&mut Command) -> &mut Command) -> Result<Vec<CommitInfo>> {
+    stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            config(
+                // We parse output as UTF-8, regardless of `i18n.logOutputEncoding`, and
+                // signatures (see `log.showSignature`) would be interleaved with it.
+                cmd.args([
+                    "log",
+                    "--topo-order",
+                    "--no-show-signature",
+                    "--encoding=UTF-8",
+                ])
+                .arg(format!("--format={}", CommitInfo::FORMAT)),
+            )
+        }),
+        false,
+    )?
+    .iter()
+    .map(|line| CommitInfo::parse(line))
+    .collect()
+}
+
+/// Preview squashing the commits of `HEAD` since its merge base with `base` into a single commit.
+///
+/// Prints the combined diffstat of those commits, followed by a suggested commit message that
+/// lists their subjects, oldest first. Merge commits are omitted from the message.
+pub fn squash_preview(base: &str) -> Result<()> {
+    EasyCommand::new_with("git", |cmd| {
+        cmd.args(["--no-pager", "diff", "--stat", "--end-of-options"])
+            .arg(format!("{base}...HEAD"))
+            .arg("--")
+    })
+    .spawn_and_wait()
+    .map_err(Error::from_spawn)
+    .and_then(Error::from_status)?;
+
+    let commits = commits_with(|cmd| {
+        cmd.args(["--reverse", "--no-merges", "--end-of-options"])
+            .arg(format!("{base}..HEAD"))
+    })?;
+    let Some(oldest) = commits.first() else {
+        log::warn!("no commits to squash");
+        return Ok(());
+    };
+    println!();
+    println!("{}", oldest.subject);
+    if commits.len() > 1 {
+        println!();
+        for commit in &commits {
+            println!("* {}", commit.subject);
+        }
+    }
+    Ok(())
+}
+
+/// Compare `old` and `new` versions of a branch forked from `base` with `git range-diff`.
+pub fn range_diff(
+    base: &str,
+    old: &str,
+    new: &str,
+    creation_factor: Option<u32>,
+    render: &RenderConfig,
+) -> Result<()> {
+    // Some versions of `git range-diff` take `--end-of-options` for a range, so pass full object
+    // names, which can't be mistaken for options, instead.
+    let [base, old, new] = [base, old, new].map(rev_parse);
+    let (base, old, new) = (base?, old?, new?);
+    EasyCommand::new_with("git", |cmd| {
+        let to_terminal = io::stdout().is_terminal();
+        if !render.pager || !to_terminal {
+            cmd.arg("--no-pager");
+        }
+        cmd.arg("range-diff");
+        match render.color {
+            ColorChoice::Auto if to_terminal => (),
+            ColorChoice::Auto => {
+                cmd.arg("--color=never");
+            }
+            ColorChoice::Always => {
+                cmd.arg("--color=always");
+            }
+            ColorChoice::Never => {
+                cmd.arg("--color=never");
+            }
+        }
+        if let Some(creation_factor) = creation_factor {
+            cmd.arg(format!("--creation-factor={creation_factor}"));
+        }
+        cmd.arg(format!("{base}..{old}"))
+            .arg(format!("{base}..{new}"))
+    })
+    .spawn_and_wait()
+    .map_err(Error::from_spawn)
+    .and_then(Error::from_status)
+}
+
+/// Print `placeholder` (i.e., `%s`) of `git log --format` for the commit at `rev`.
+pub fn show_field(rev: &str, placeholder: &str) -> Result<()> {
+    if !placeholder.starts_with('%') || placeholder.len() < 2 {
+        return Err(Error::other(anyhow!(
+            "expected a `--format` placeholder like `%s`, but got {placeholder:?}"
+        )));
+    }
+    EasyCommand::new_with("git", |cmd| {
+        cmd.args(["--no-pager", "log", "--max-count=1", "--no-show-signature"])
+            .arg(format!("--format={placeholder}"))
+            .arg("--end-of-options")
+            .arg(rev)
+            .arg("--")
+    })
+    .spawn_and_wait()
+    .map_err(Error::from_spawn)
+    .and_then(Error::from_status)
+}
+
+/// Selection options for the "stack" of commits relevant to the currently checked out branch.
+#[derive(Clone, Debug)]
+pub struct StackOptions {
+    /// The mainline branch. Falls back to the `glimpse.base` config, then to [`upstream_base`],
+    /// and then to `main`.
+    pub base: Option<String>,
+    pub config: PresetConfig,
+    /// Cut off history at [`first_parent_merge_base`] of the base and `HEAD`, rather than the
+    /// octopus merge base of all selected branches.
+    pub first_parent_base: bool,
+    /// What to select when the current branch is the base branch itself.
+    pub on_base: OnBaseMode,
+    /// How many of the most recent commits or merges of the base branch to keep for
+    /// [`Self::on_base`]. Defaults to 10.
+    pub on_base_count: usize,
+}
+
+impl Default for StackOptions {
+    fn default() -> Self {
+        Self {
+            base: None,
+            config: PresetConfig::default(),
+            first_parent_base: false,
+            on_base: OnBaseMode::default(),
+            on_base_count: 10,
+        }
+    }
+}
+
+/// Find where to cut off history of the stack selected by [`select_stack`] with `options`.
+///
+/// Returns the merge base to use instead of the octopus merge base of the selection (see
+/// [`GraphOptions::merge_base`]), and, when the current branch is the base branch itself, the
+/// commits whose history to exclude (see [`GraphOptions::since`]) from [`on_base_cutoff`].
+pub fn stack_cutoff(options: &StackOptions) -> Result<(Option<String>, Option<Vec<String>>)> {
+    let base = resolve_base(options.base.clone())?;
+    let merge_base = if options.first_parent_base {
+        Some(first_parent_merge_base(&base, "HEAD")?)
+    } else {
+        None
+    };
+    let since = if current_branch()?.as_deref() == Some(&*base) {
+        on_base_cutoff(&base, options.on_base, options.on_base_count)?
+    } else {
+        None
+    };
+    Ok((merge_base, since))
+}
+
+/// What [`select_stack`] selects when the current branch is the base branch itself.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum OnBaseMode {
+    /// The base branch and its upstream, i.e., to see what's unpushed and unpulled.
+    #[default]
+    Upstream,
+    /// The base branch alone, with history cut off by [`on_base_cutoff`] after its most recent
+    /// commits.
+    Recent,
+    /// The base branch alone, with history cut off by [`on_base_cutoff`] after its most recent
+    /// merges, along with the commits they merged.
+    Merges,
+}
+
+/// Find where to cut off history of `base` (i.e., for [`GraphOptions::since`]) for `mode`, when
+/// the current branch is `base` itself, keeping `count` of its most recent commits or merges.
+///
+/// Only the first-parent history of `base` is counted. [`OnBaseMode::Upstream`] cuts off history
+/// at the merge base as usual, so `None` is returned for it.
+pub fn on_base_cutoff(base: &str, mode: OnBaseMode, count: usize) -> Result<Option<Vec<String>>> {
+    match mode {
+        OnBaseMode::Upstream => Ok(None),
+        OnBaseMode::Recent => {
+            let commits = stdout_lines(
+                EasyCommand::new_with("git", |cmd| {
+                    cmd.args(["rev-list", "--first-parent"])
+                        .arg(format!("--max-count={}", count + 1))
+                        .arg("--end-of-options")
+                        .arg(base)
+                }),
+                true,
+            )?;
+            // With no more than `count` commits, all history is recent.
+            Ok(Some(commits.get(count).cloned().into_iter().collect()))
+        }
+        OnBaseMode::Merges => {
+            let merges = stdout_lines(
+                EasyCommand::new_with("git", |cmd| {
+                    cmd.args(["rev-list", "--first-parent", "--merges"])
+                        .arg(format!("--max-count={count}"))
+                        .arg("--end-of-options")
+                        .arg(base)
+                }),
+                true,
+            )?;
+            let oldest = merges.last().ok_or_else(|| {
+                Error::other(anyhow!(
+                    "no merges found in the first-parent history of {base:?}"
+                ))
+            })?;
+            Ok(Some(vec![format!("{oldest}^1")]))
+        }
+    }
+}
+
+/// Return the metadata of the commits that [`show_graph`] would display for `object_names` and
+/// `files` with `options`, in topological order. Only the options selecting commits, like
+/// [`GraphOptions::since`] and [`GraphOptions::max_count`], apply.
+///
+/// This is the programmatic counterpart to [`show_graph`], for callers that render commits
+/// themselves.
+pub fn graph_commits<'o, 'f, Os, Fs>(
+    options: &GraphOptions,
+    object_names: Os,
+    files: Fs,
+) -> Result<Vec<CommitInfo>>
+where
+    Os: IntoIterator<Item = &'o str> + Clone,
+    Fs: IntoIterator<Item = &'f OsStr>,
+{
+    log_commits(options, object_names, files)
+}
+
+/// Return the commit metadata of the current stack (see [`select_stack`]), in topological order.
+///
+/// This is the programmatic counterpart to calling [`show_graph`] with a stack selection.
+pub fn stack_commits<'a, Fs>(options: &StackOptions, files: Fs) -> Result<Vec<CommitInfo>>
+where
+    Fs: IntoIterator<Item = &'a OsStr>,
+{
+    let branches = select_stack(options)?;
+    let (merge_base, since) = stack_cutoff(options)?;
+    let options = GraphOptions {
+        merge_base,
+        since,
+        ..GraphOptions::default()
+    };
+    log_commits(&options, branches.iter().map(|s| s.as_str()), files)
+}
+
+#[derive(Clone, Debug, Default, Parser)]
+pub struct PresetConfig {
+    /// Also include all `@{upstream}` counterparts to selected branches.
+    #[clap(long = "upstreams", short = 'u')]
+    pub select_upstreams: bool,
+    /// Also select all `@{push}` counterparts to selected branches.
+    #[clap(long = "pushes", short = 'p')]
+    pub select_pushes: bool,
+    /// Also select the last tag that contains `HEAD`.
+    #[clap(long = "last-tag")]
+    pub select_last_tag: bool,
+    /// Also select remote-tracking branches, except for symbolic refs like `origin/HEAD`.
+    #[clap(long = "remotes", short = 'r')]
+    pub select_remotes: bool,
+}
+
+/// Return the root of the current working tree, or `None` if there isn't one, i.e., in a bare
+/// repository.
+pub fn worktree_root() -> Result<Option<PathBuf>> {
+    let Output { stdout, status, .. } =
+        EasyCommand::new_with("git", |cmd| cmd.args(["rev-parse", "--show-toplevel"]))
+            .output()
+            .map_err(Error::from_spawn)?;
+    Ok(status
+        .success()
+        .then(|| PathBuf::from(String::from_utf8_lossy(&stdout).trim_end())))
+}
+
+/// Return the currently checked out branch, or `None` if `HEAD` is detached.
+pub fn current_branch() -> Result<Option<String>> {
+    stdout_lines(
+        EasyCommand::new_with("git", |cmd| cmd.args(["branch", "--show-current"])),
+        true,
+    )
+    .map(|mut lines| {
+        let current_branch = lines.pop();
+        log::trace!("current branch: {current_branch:?}");
+        log::trace!("`HEAD` is detached: {:?}", current_branch.is_none());
+        debug_assert!(lines.is_empty());
+        current_branch
+    })
+}
+
+/// Select branches listed by [`list_branches_cmd`] (further configured with `cmd_config`), along
+/// with their counterparts requested by `config`.
+///
+/// Patterns specified by `cmd_config` should follow an `--end-of-options` argument, so they can't
+/// be misinterpreted as options.
+pub fn select_branches(
+    config: &PresetConfig,
+    cmd_config: &dyn Fn(&mut Command) -> &mut Command,
+) -> Result<Vec<String>> {
+    let PresetConfig {
+        select_last_tag,
+        select_remotes,
+        ..
+    } = config;
+    let format = branch_format(config, current_branch()?.is_none());
+    check_balanced_ifs(&format)?;
+
+    let mut branches = ref_lines(list_branches_cmd(|cmd| {
+        if *select_remotes {
+            cmd.arg("--all");
+        }
+        cmd_config(cmd.arg(format))
+    }))?;
+    if *select_remotes {
+        branches.retain(|branch| !branch.is_empty());
+    }
+
+    if *select_last_tag {
+        match stdout_lines(
+            EasyCommand::new_with("git", |cmd| {
+                cmd.args(["rev-list", "--tags", "--max-count=1"])
+            }),
+            true,
+        )?
+        .pop()
+        {
+            Some(last_tag) => branches.push(last_tag),
+            None => log::warn!("last tag requested, but no last tag was found"),
+        }
+    }
+
+    Ok(branches)
+}
+
+/// The `--format` argument with which [`select_branches`] lists each branch, along with the
+/// counterparts requested by `config`, one per line.
+fn branch_format(config: &PresetConfig, head_is_detached: bool) -> String {
+    let PresetConfig {
+        select_upstreams,
+        select_pushes,
+        select_last_tag: _,
+        select_remotes,
+    } = config;
+
+    let mut format = "--format=".to_owned();
+    // Symbolic refs, like `origin/HEAD`, point at branches that are selected anyway, and their
+    // short names (i.e., `origin`) aren't unambiguous object names. These become empty lines.
+    if *select_remotes {
+        format.push_str("%(if)%(symref)%(then)%(else)");
+    }
+    if head_is_detached {
+        format.push_str("%(if)%(HEAD)%(then)HEAD%(else)");
+    }
+    format.push_str("%(refname:short)");
+    let mut include_in_format = |prop_name: &str| {
+        format += &format!("%(if)%({prop_name})%(then)\n%({prop_name}:short)%(end)");
+    };
+    if *select_upstreams {
+        include_in_format("upstream");
+    }
+    if *select_pushes {
+        include_in_format("push");
+    }
+    if head_is_detached {
+        format.push_str("%(end)");
+    }
+    if *select_remotes {
+        format.push_str("%(end)");
+    }
+    format
+}
+
+/// Check that each `%(if)` of a `for-each-ref`-style `format` is closed by an `%(end)`, since Git's
+/// own error for an imbalance doesn't point at the format we built.
+///
+/// This panics in debug builds, since an imbalance is a bug in building the format.
+fn check_balanced_ifs(format: &str) -> Result<()> {
+    let ifs = format.matches("%(if)").count() + format.matches("%(if:").count();
+    let ends = format.matches("%(end)").count();
+    debug_assert_eq!(ifs, ends, "unbalanced `%(if)`s and `%(end)`s in {format:?}");
+    if ifs != ends {
+        return Err(Error::other(anyhow!(
+            "internal error: format has {ifs} `%(if)`s, but {ends} `%(end)`s: {format:?}"
+        )));
+    }
+    Ok(())
+}
+
+/// Select local branches that are merged into `base`, and whose tips were committed longer ago
+/// than `older_than`, as candidates for deletion. `base` itself and the current branch are never
+/// selected.
+pub fn select_stale(base: &str, older_than: Duration) -> Result<Vec<String>> {
+    let cutoff = cutoff(older_than);
+    let current = current_branch()?;
+    let branches = ref_lines(list_branches_cmd(|cmd| {
+        cmd.arg("--format=%(refname:short)%00%(committerdate:unix)")
+            .arg(format!("--merged={base}"))
+    }))?;
+    Ok(branches
+        .iter()
+        .filter_map(|line| {
+            let (branch, committed) = line.split_once('\0')?;
+            let committed = committed.parse::<i64>().ok()?;
+            let stale = committed < cutoff && branch != base && current.as_deref() != Some(branch);
+            stale.then(|| branch.to_owned())
+        })
+        .collect())
+}
+
+/// Drop those of `branches` whose names match any of the glob `patterns`, like `wip/*`.
+///
+/// `*` matches any sequence of characters, including `/`, and `?` matches any single character,
+/// like the patterns of `git branch --list`.
+pub fn exclude_branches(branches: Vec<String>, patterns: &[String]) -> Vec<String> {
+    fn glob_matches(pattern: &[char], name: &[char]) -> bool {
+        match (pattern.split_first(), name.split_first()) {
+            (None, None) => true,
+            (Some(('*', rest)), _) => {
+                glob_matches(rest, name)
+                    || name
+                        .split_first()
+                        .is_some_and(|(_, name)| glob_matches(pattern, name))
+            }
+            (Some(('?', rest)), Some((_, name))) => glob_matches(rest, name),
+            (Some((p, rest)), Some((c, name))) if p == c => glob_matches(rest, name),
+            _ => false,
+        }
+    }
+
+    let patterns = patterns
+        .iter()
+        .map(|pattern| pattern.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    branches
+        .into_iter()
+        .filter(|branch| {
+            let name = branch.chars().collect::<Vec<_>>();
+            let excluded = patterns.iter().any(|pattern| glob_matches(pattern, &name));
+            if excluded {
+                log::debug!("excluding branch {branch:?}");
+            }
+            !excluded
+        })
+        .collect()
+}
+
+/// Local branches, and remote-tracking branches if `remotes` is set, whose tips were committed
+/// longer ago than `active_within`.
+pub fn inactive_branches(active_within: Duration, remotes: bool) -> Result<Vec<String>> {
+    let cutoff = cutoff(active_within);
+    let branches = ref_lines(list_branches_cmd(|cmd| {
+        cmd.arg("--format=%(refname:short)%00%(committerdate:unix)");
+        if remotes {
+            cmd.arg("--all");
+        }
+        cmd
+    }))?;
+    Ok(branches
+        .iter()
+        .filter_map(|line| {
+            let (branch, committed) = line.split_once('\0')?;
+            let committed = committed.parse::<i64>().ok()?;
+            (committed < cutoff).then(|| branch.to_owned())
+        })
+        .collect())
+}
+
+/// Instead of a graph, list each branch listed by [`list_branches_cmd`] (further configured with
+/// `cmd_config`), each immediately followed by how it has diverged from its upstream. Branches
+/// matching `exclude` are omitted, as by [`exclude_branches`].
+pub fn show_upstream_divergence(
+    cmd_config: &dyn Fn(&mut Command) -> &mut Command,
+    exclude: &[String],
+) -> Result<()> {
+    let lines = ref_lines(list_branches_cmd(|cmd| {
+        cmd_config(
+            cmd.arg("--format=%(refname:short)%00%(upstream:short)%00%(upstream:track,nobracket)"),
+        )
+    }))?;
+    let branches = lines
+        .iter()
+        .filter_map(|line| line.split('\0').next())
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+    let kept = exclude_branches(branches.clone(), exclude)
+        .into_iter()
+        .collect::<BTreeSet<_>>();
+    if kept.is_empty() && !branches.is_empty() {
+        log::info!("all branches were excluded");
+        return Ok(());
+    }
+    for line in &lines {
+        let mut fields = line.split('\0');
+        let (Some(branch), upstream, track) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        if !kept.contains(branch) {
+            continue;
+        }
+        println!("{branch}");
+        match (upstream.filter(|upstream| !upstream.is_empty()), track) {
+            (None, _) => println!("    (no upstream)"),
+            (Some(upstream), None | Some("")) => println!("    {upstream}: up to date"),
+            (Some(upstream), Some(track)) => println!("    {upstream}: {track}"),
+        }
+    }
+    Ok(())
+}
+
+/// Sort `branches` by how many commits each is ahead of `base`, descending.
+pub fn sort_by_ahead(branches: &mut Vec<String>, base: &str) -> Result<()> {
+    let mut counted = branches
+        .drain(..)
+        .map(|branch| {
+            let count = stdout_lines(
+                EasyCommand::new_with("git", |cmd| {
+                    cmd.args(["rev-list", "--count", "--end-of-options"])
+                        .arg(format!("{base}..{branch}"))
+                }),
+                true,
+            )?
+            .first()
+            .and_then(|count| count.parse::<usize>().ok())
+            .unwrap_or(0);
+            Ok((count, branch))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    counted.sort_by(|(a, _), (b, _)| b.cmp(a));
+    branches.extend(counted.into_iter().map(|(_, branch)| branch));
+    Ok(())
+}
+
+/// Select tags, with their listing configured by `cmd_config` (i.e., `--contains`).
+pub fn select_tags(cmd_config: &dyn Fn(&mut Command) -> &mut Command) -> Result<Vec<String>> {
+    let tags = ref_lines(EasyCommand::new_with("git", |cmd| {
+        cmd_config(cmd.args(["tag", "--list", "--format=%(refname)"]))
+    }))?;
+    if tags.is_empty() {
+        return Err(Error::other(anyhow!("no tags were selected")));
+    }
+    Ok(tags)
+}
+
+/// The commits between two adjacent releases, as listed by [`release_ranges`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReleaseRange {
+    /// The full ref name of the older release's tag.
+    pub from: String,
+    /// The full ref name of the newer release's tag.
+    pub to: String,
+    /// The number of commits reachable from [`Self::to`], but not [`Self::from`].
+    pub commits: usize,
+}
+
+/// List the ranges between adjacent tags matching `pattern` (see `git tag --list`), sorted as
+/// versions, newest first.
+///
+/// Only the `limit` newest ranges are listed, if specified. Commits are counted as touching any
+/// of `files`, if there are any.
+pub fn release_ranges(
+    pattern: &str,
+    limit: Option<usize>,
+    files: &[&OsStr],
+) -> Result<Vec<ReleaseRange>> {
+    let tags = ref_lines(EasyCommand::new_with("git", |cmd| {
+        cmd.args([
+            "tag",
+            "--list",
+            "--sort=-version:refname",
+            "--format=%(refname)",
+            "--end-of-options",
+            pattern,
+        ])
+    }))?;
+    if tags.len() < 2 {
+        return Err(Error::other(anyhow!(
+            "at least two tags matching {pattern:?} are needed for a release range"
+        )));
+    }
+    tags.windows(2)
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|tags| {
+            let [to, from] = tags else {
+                unreachable!("windows are of two tags");
+            };
+            let count = stdout_lines(
+                EasyCommand::new_with("git", |cmd| {
+                    cmd.args(["rev-list", "--count", "--end-of-options"])
+                        .arg(format!("{from}..{to}"))
+                        .arg("--")
+                        .args(files)
+                }),
+                true,
+            )?;
+            Ok(ReleaseRange {
+                from: from.clone(),
+                to: to.clone(),
+                commits: count
+                    .first()
+                    .and_then(|count| count.parse().ok())
+                    .unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+/// Select refs matching any of `patterns` (see `git for-each-ref`), i.e., those in a custom
+/// namespace like Gerrit's `refs/changes/`.
+pub fn select_refs(patterns: &[String]) -> Result<Vec<String>> {
+    let refs = ref_lines(EasyCommand::new_with("git", |cmd| {
+        cmd.args(["for-each-ref", "--format=%(refname)", "--end-of-options"])
+            .args(patterns)
+    }))?;
+    if refs.is_empty() {
+        return Err(Error::other(anyhow!("no refs match {patterns:?}")));
+    }
+    Ok(refs)
+}
+
+/// Select the first `count` commits of history from each root commit (i.e., one without parents)
+/// reachable from `HEAD`, or from all refs if `all` is set.
+///
+/// Returns the selected commits, along with the commits whose history must be excluded (see
+/// [`GraphOptions::since`]) to show only them. Commits of other roots' history merged into that
+/// of a root are excluded this way.
+pub fn select_roots(count: usize, all: bool) -> Result<(Vec<String>, Vec<String>)> {
+    let tips = if all { "--all" } else { "HEAD" };
+    let roots = stdout_lines(
+        EasyCommand::new_with("git", |cmd| cmd.args(["rev-list", "--max-parents=0", tips])),
+        true,
+    )?;
+    log::debug!("found root commits {roots:?}");
+    let mut parents_by_commit = ParentsByCommit::new();
+    for root in &roots {
+        parents_by_commit.extend([root.clone()]);
+        let descendants = stdout_lines(
+            EasyCommand::new_with("git", |cmd| {
+                cmd.args([
+                    "rev-list",
+                    "--parents",
+                    "--topo-order",
+                    "--reverse",
+                    "--ancestry-path",
+                    tips,
+                ])
+                .arg(format!("^{root}"))
+            }),
+            true,
+        )?;
+        // Parents precede their children in this order, so all parents of these commits that also
+        // descend from the root are among them.
+        parents_by_commit.extend(descendants.into_iter().take(count.saturating_sub(1)));
+    }
+    let boundary = parents_by_commit
+        .boundary()
+        .into_iter()
+        .map(ToOwned::to_owned)
+        .collect();
+    Ok((parents_by_commit.0.into_keys().collect(), boundary))
+}
+
+/// Select the commits reachable from `tips` (or from all refs, if there are none) whose messages
+/// match the basic regular expression `pattern` (see `git log --grep`), along with their parents
+/// for context.
+///
+/// Returns the selected commits, along with the commits whose history must be excluded (see
+/// [`GraphOptions::since`]) to show only them, like [`select_roots`].
+pub fn select_from_log(
+    pattern: &str,
+    tips: &[String],
+    ignore_case: bool,
+) -> Result<(Vec<String>, Vec<String>)> {
+    let matches = stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            cmd.args([
+                "log",
+                "--no-show-signature",
+                "--format=%H",
+                "--basic-regexp",
+            ])
+            .arg(format!("--grep={pattern}"));
+            if ignore_case {
+                cmd.arg("--regexp-ignore-case");
+            }
+            if tips.is_empty() {
+                cmd.arg("--all");
+            } else {
+                cmd.arg("--end-of-options").args(tips);
+            }
+            cmd.arg("--")
+        }),
+        true,
+    )?;
+    if matches.is_empty() {
+        return Err(Error::other(anyhow!(
+            "no commit messages match {pattern:?}"
+        )));
+    }
+    log::debug!("found {} commits matching {pattern:?}", matches.len());
+    let with_parents = |commits: Vec<&str>| -> Result<Vec<String>> {
+        let revisions = Revisions::new(Vec::new(), commits)?;
+        stdout_lines(
+            EasyCommand::new_with("git", |cmd| {
+                cmd.args([
+                    "log",
+                    "--no-walk=unsorted",
+                    "--no-show-signature",
+                    "--format=%H %P",
+                ]);
+                revisions.pass_to(cmd)
+            }),
+            true,
+        )
+    };
+    let mut parents_by_commit = ParentsByCommit::new();
+    parents_by_commit.extend(with_parents(matches.iter().map(String::as_str).collect())?);
+    let parents = parents_by_commit.boundary().into_iter().collect::<Vec<_>>();
+    if !parents.is_empty() {
+        let parents = with_parents(parents)?;
+        parents_by_commit.extend(parents);
+    }
+    let boundary = parents_by_commit
+        .boundary()
+        .into_iter()
+        .map(ToOwned::to_owned)
+        .collect();
+    Ok((parents_by_commit.0.into_keys().collect(), boundary))
+}
+
+/// Return the local branches not merged into `upstream` whose changes have nonetheless all landed
+/// in it, like with a squash merge.
+///
+/// A branch has landed if the patch ID (see `git patch-id --stable`) of its changes since its merge
+/// base with `upstream`, or those of each of its commits since, are among those of the commits of
+/// `upstream` since. Nothing is written to the repository, unlike with `git commit-tree`.
+pub fn squash_merged_branches(upstream: &str) -> Result<Vec<String>> {
+    let upstream_oid = rev_parse(upstream)?;
+    let branches = ref_lines(list_branches_cmd(|cmd| {
+        cmd.arg(format!("--no-merged={upstream_oid}"))
+    }))?;
+    let mut upstream_patch_ids = BTreeMap::<String, BTreeSet<String>>::new();
+    let mut squashed = Vec::new();
+    for branch in branches {
+        let Output { stdout, status, .. } = EasyCommand::new_with("git", |cmd| {
+            cmd.args(["merge-base", "--end-of-options", &upstream_oid, &branch])
+        })
+        .output()
+        .map_err(Error::from_spawn)?;
+        if status.code() == Some(1) {
+            log::trace!("{branch:?} has no merge base with {upstream:?}");
+            continue;
+        }
+        Error::from_status(status)?;
+        let merge_base = String::from_utf8_lossy(&stdout).trim().to_owned();
+        let landed = match upstream_patch_ids.entry(merge_base.clone()) {
+            btree_map::Entry::Occupied(entry) => entry.into_mut(),
+            btree_map::Entry::Vacant(entry) => {
+                entry.insert(patch_ids(patches(&merge_base, &upstream_oid, false)?)?)
+            }
+        };
+        if landed.is_empty() {
+            continue;
+        }
+        let squash = patch_ids(patches(&merge_base, &branch, true)?)?;
+        let picks = patch_ids(patches(&merge_base, &branch, false)?)?;
+        let is_subset = |ids: &BTreeSet<String>| !ids.is_empty() && ids.is_subset(landed);
+        if is_subset(&squash) || is_subset(&picks) {
+            log::debug!("{branch:?} appears to have been squash-merged into {upstream:?}");
+            squashed.push(branch);
+        }
+    }
+    Ok(squashed)
+}
+
+/// The patches of the commits in `merge_base..tip`, like `git log -p`, or, if `squash`, that of
+/// their combined changes, like `git diff`.
+fn patches(merge_base: &str, tip: &str, squash: bool) -> Result<Vec<u8>> {
+    let Output { stdout, status, .. } = EasyCommand::new_with("git", |cmd| {
+        if squash {
+            cmd.arg("diff");
+        } else {
+            cmd.args(["log", "-p", "--no-show-signature", "--no-merges"])
+                .arg("--format=commit %H");
+        }
+        cmd.args(["--no-color", "--no-ext-diff", "--end-of-options"]);
+        if squash {
+            cmd.args([merge_base, tip])
+        } else {
+            cmd.arg(format!("{merge_base}..{tip}"))
+        }
+        .arg("--")
+    })
+    .output()
+    .map_err(Error::from_spawn)?;
+    Error::from_status(status)?;
+    Ok(stdout)
+}
+
+/// The stable patch IDs (see `git patch-id --stable`) of `patches`, as output by [`patches`].
+fn patch_ids(patches: Vec<u8>) -> Result<BTreeSet<String>> {
+    if patches.is_empty() {
+        return Ok(BTreeSet::new());
+    }
+    log::debug!("getting output from `git patch-id --stable`…");
+    let mut child = Command::new("git")
+        .args(["patch-id", "--stable"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("failed to spawn `git patch-id`")
+        .map_err(Error::from_spawn)?;
+    let mut stdin = child.stdin.take().unwrap();
+    // Write from another thread, so that neither process blocks on a full pipe to the other.
+    let writer = thread::spawn(move || stdin.write_all(&patches));
+    let output = child
+        .wait_with_output()
+        .context("failed to wait for `git patch-id` to exit")
+        .map_err(Error::other)?;
+    let written = writer.join().expect("writing to `git patch-id` panicked");
+    let lines = lines_of(output, true)?;
+    written
+        .context("failed to write to `git patch-id`")
+        .map_err(Error::other)?;
+    Ok(lines
+        .iter()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(ToOwned::to_owned)
+        .collect())
+}
+
+/// Select the current "stack" of commits: the currently checked out branch (or `HEAD`, if
+/// detached) and the mainline branch.
+pub fn select_stack(options: &StackOptions) -> Result<Vec<String>> {
+    let StackOptions {
+        base,
+        config,
+        first_parent_base: _,
+        on_base,
+        on_base_count: _,
+    } = options;
+    let base = &*resolve_base(base.clone())?;
+
+    if let Some(current_branch) = current_branch()? {
+        let mut config = config.clone();
+        if current_branch == base && *on_base == OnBaseMode::Upstream {
+            config.select_upstreams = true;
+        }
+        select_branches(&config, &|cmd| {
+            cmd.args(["--all", "--end-of-options"]);
+            if base != current_branch {
+                cmd.arg(base);
+            }
+            cmd.arg(&current_branch)
+        })
+    } else {
+        let mut branches = select_branches(config, &|cmd| {
+            cmd.args(["--all", "--end-of-options"]).arg(base)
+        })?;
+        branches.push("HEAD".to_owned());
+        Ok(branches)
+    }
+}
+
+/// Select the range of commits of an ongoing `git bisect` session, along with markers for its
+/// good, bad and skipped commits.
+///
+/// The range spans from the bad commit (or `HEAD`, if none has been marked yet) down to the good
+/// commits. Returns the selected commits, the commits whose history must be excluded (see
+/// [`GraphOptions::since`]) to show only that range, i.e., the parents of the good commits, and
+/// the markers.
+pub fn select_bisect() -> Result<(Vec<String>, Vec<String>, Vec<CommitMarker>)> {
+    let term = |which| -> Result<_> {
+        let mut lines = stdout_lines(
+            EasyCommand::new_with("git", |cmd| cmd.args(["bisect", "terms", which])),
+            true,
+        )?;
+        lines
+            .pop()
+            .ok_or_else(|| Error::other(anyhow!("`git bisect terms {which}` printed nothing")))
+    };
+    let bad_term = term("--term-bad")?;
+    let good_term = term("--term-good")?;
+
+    let mut bad = None;
+    let mut good = Vec::new();
+    let mut skipped = Vec::new();
+    for line in ref_lines(EasyCommand::new_with("git", |cmd| {
+        cmd.args([
+            "for-each-ref",
+            "--format=%(objectname) %(refname)",
+            "refs/bisect/",
+        ])
+    }))? {
+        let Some((oid, refname)) = line.split_once(' ') else {
+            continue;
+        };
+        let Some(name) = refname.strip_prefix("refs/bisect/") else {
+            continue;
+        };
+        if name == bad_term {
+            bad = Some(oid.to_owned());
+        } else if name
+            .strip_prefix(&*good_term)
+            .is_some_and(|rest| rest.starts_with('-'))
+        {
+            good.push(oid.to_owned());
+        } else if name.starts_with("skip-") {
+            skipped.push(oid.to_owned());
+        }
+    }
+    if good.is_empty() {
+        return Err(Error::other(anyhow!(
+            "no {good_term:?} commits have been marked in the current bisect session"
+        )));
+    }
+
+    let bad = match bad {
+        Some(bad) => bad,
+        None => {
+            log::info!("no {bad_term:?} commit marked yet, using `HEAD` as the tip of the range");
+            rev_parse("HEAD")?
+        }
+    };
+    let mut markers = vec![CommitMarker {
+        commit: bad.clone(),
+        description: format!("the commit marked as `{bad_term}` in the bisection"),
+        marker: bad_term,
+        color: Some(MarkerColor::Red),
+    }];
+    markers.extend(good.iter().map(|commit| CommitMarker {
+        commit: commit.clone(),
+        marker: good_term.clone(),
+        color: Some(MarkerColor::Green),
+        description: format!("commits marked as `{good_term}` in the bisection"),
+    }));
+    markers.extend(skipped.into_iter().map(|commit| CommitMarker {
+        commit,
+        marker: "skip".to_owned(),
+        color: Some(MarkerColor::Yellow),
+        description: "commits skipped in the bisection".to_owned(),
+    }));
+    // Unlike cutting history off at the merge base of all of these, this leaves out commits below
+    // any good commit, which aren't candidates anymore. Good commits below others are left out
+    // too, then.
+    let boundary = stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            cmd.args(["rev-list", "--no-walk", "--parents", "--end-of-options"])
+                .args(&good)
+        }),
+        true,
+    )?
+    .iter()
+    .flat_map(|line| line.split_whitespace().skip(1))
+    .map(ToOwned::to_owned)
+    .collect();
+    let mut selection = vec![bad];
+    selection.extend(good);
+    Ok((selection, boundary, markers))
+}
+
+/// Build a basic regular expression for [`GraphOptions::author`] matching exactly the email
+/// configured in `user.email`.
+pub fn author_pattern_for_self() -> Result<String> {
+    let email = git_config("user.email")?.ok_or_else(|| {
+        Error::other(anyhow!(
+            "`user.email` is not configured; set it with `git config user.email <email>`"
+        ))
+    })?;
+    let mut pattern = "<".to_owned();
+    for c in email.chars() {
+        if "\\.^$*[]".contains(c) {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('>');
+    Ok(pattern)
+}
+
+/// Resolve the mainline branch, falling back to the `glimpse.base` config, then to
+/// [`upstream_base`], and then to `main`.
+pub fn resolve_base(base: Option<String>) -> Result<String> {
+    let specified_base = base
+        .map(Ok)
+        .or_else(|| git_config("glimpse.base").transpose())
+        .transpose()?;
+    if let Some(base) = specified_base {
+        return Ok(base);
+    }
+    if let Some(upstream) = upstream_base()? {
+        log::debug!(
+            "no base branch specified in command line or configuration, using upstream of \
+            current branch: {upstream:?}"
+        );
+        return Ok(upstream);
+    }
+    let default = "main";
+    log::debug!(
+        "no base branch specified in command line or configuration, and current branch has no \
+        upstream to use, falling back to {default:?}"
+    );
+    Ok(default.to_owned())
+}
+
+/// Deduce the mainline branch from the upstream of the current branch, i.e., `develop` for a
+/// branch created with `git switch -c feature origin/develop`.
+///
+/// The local counterpart of a remote-tracking upstream is preferred, if it exists. An upstream
+/// with the same name as the current branch is ignored, since it's more likely where the current
+/// branch is pushed to than what it's based on.
+pub fn upstream_base() -> Result<Option<String>> {
+    let Some(branch) = current_branch()? else {
+        return Ok(None);
+    };
+    let upstream = ref_lines(EasyCommand::new_with("git", |cmd| {
+        cmd.args([
+            "for-each-ref",
+            "--format=%(upstream)%00%(upstream:remotename)",
+            "--end-of-options",
+        ])
+        .arg(format!("refs/heads/{branch}"))
+    }))?;
+    let Some((upstream, remote)) = upstream.first().and_then(|line| line.split_once('\0')) else {
+        return Ok(None);
+    };
+    if let Some(local) = upstream.strip_prefix("refs/heads/") {
+        return Ok((local != branch).then(|| local.to_owned()));
+    }
+    let Some(name) = upstream
+        .strip_prefix("refs/remotes/")
+        .and_then(|upstream| upstream.strip_prefix(remote))
+        .and_then(|upstream| upstream.strip_prefix('/'))
+    else {
+        return Ok(None);
+    };
+    if name == branch {
+        return Ok(None);
+    }
+    let Output { status, .. } = EasyCommand::new_with("git", |cmd| {
+        cmd.args(["show-ref", "--verify", "--quiet", "--end-of-options"])
+            .arg(format!("refs/heads/{name}"))
+    })
+    .output()
+    .map_err(Error::from_spawn)?;
+    Ok(Some(if status.success() {
+        name.to_owned()
+    } else {
+        format!("{remote}/{name}")
+    }))
+}
+
+/// Log, at the `info` level, each candidate for the base branch that [`resolve_base`] considers
+/// given `base`, whether it exists, and which is selected.
+pub fn explain_base(base: Option<&str>) -> Result<()> {
+    let candidates = [
+        ("`--base`", base.map(ToOwned::to_owned)),
+        ("`glimpse.base` config", git_config("glimpse.base")?),
+        ("upstream of current branch", upstream_base()?),
+        ("default", Some("main".to_owned())),
+    ];
+    let mut selected = None;
+    for (source, candidate) in candidates {
+        let Some(candidate) = candidate else {
+            log::info!("base candidate from {source}: unset");
+            continue;
+        };
+        let Output { status, .. } = EasyCommand::new_with("git", |cmd| {
+            cmd.args(["rev-parse", "--verify", "--quiet", "--end-of-options"])
+                .arg(format!("{candidate}^{{commit}}"))
+        })
+        .output()
+        .map_err(Error::from_spawn)?;
+        let exists = if status.success() {
+            "exists"
+        } else {
+            "does not exist"
+        };
+        log::info!("base candidate from {source}: {candidate:?}, which {exists}");
+        selected.get_or_insert(candidate);
+    }
+    if let Some(selected) = selected {
+        log::info!("selected base: {selected:?}");
+    }
+    Ok(())
+}
+
+/// Print, for the base branch and the `git log` format, each source that is consulted in order of
+/// precedence, its value, and which one wins.
+///
+/// `base` and `format` are the values given on the command line, if any.
+pub fn show_config_resolution(
+    base: Option<&str>,
+    format: Option<&str>,
+    responsive_format: bool,
+    committer_date: bool,
+) -> Result<()> {
+    fn print_chain(setting: &str, candidates: Vec<(String, Option<String>)>) {
+        println!("{setting}:");
+        let mut selected = false;
+        for (source, candidate) in candidates {
+            match candidate {
+                None => println!("    {source}: unset"),
+                Some(candidate) if !selected => {
+                    selected = true;
+                    println!("  * {source}: {candidate:?}");
+                }
+                Some(candidate) => println!("    {source}: {candidate:?}"),
+            }
+        }
+    }
+
+    let default = default_format(committer_date);
+    let format_default = if responsive_format {
+        let width = terminal_size::terminal_size().map(|(width, _height)| width.0);
+        let format = if width.is_some_and(|width| width >= RESPONSIVE_FORMAT_MIN_WIDTH) {
+            default
+        } else {
+            TERSE_FORMAT
+        };
+        let width = width.map_or_else(|| "unknown".to_owned(), |width| width.to_string());
+        (
+            format!("`--responsive-format` (terminal width: {width})"),
+            Some(format.to_owned()),
+        )
+    } else {
+        ("default".to_owned(), Some(default.to_owned()))
+    };
+    print_chain(
+        "base",
+        vec![
+            ("`--base`".to_owned(), base.map(ToOwned::to_owned)),
+            config_source("glimpse.base")?,
+            ("upstream of current branch".to_owned(), upstream_base()?),
+            ("default".to_owned(), Some("main".to_owned())),
+        ],
+    );
+    print_chain(
+        "format",
+        vec![
+            ("`--format`".to_owned(), format.map(ToOwned::to_owned)),
+            config_source("glimpse.pretty")?,
+            format_default,
+        ],
+    );
+    Ok(())
+}
+
+/// Describe where the config at `path` is set, if anywhere, for [`show_config_resolution`].
+fn config_source(path: &str) -> Result<(String, Option<String>)> {
+    let mut cmd = EasyCommand::new_with("git", |cmd| {
+        cmd.args(["config", "--show-origin", "--show-scope", "--get", path])
+    });
+    let origin = git_config_lines(&mut cmd)?.and_then(|mut lines| lines.next());
+    Ok(
+        match origin.as_deref().and_then(|line| {
+            let (scope, rest) = line.split_once('\t')?;
+            let (origin, value) = rest.split_once('\t')?;
+            Some((scope, origin, value))
+        }) {
+            Some((scope, origin, value)) => (
+                format!("`{path}` config ({scope}, {origin})"),
+                Some(value.to_owned()),
+            ),
+            None => (format!("`{path}` config"), None),
+        },
+    )
+}
+
+/// Log, at the `info` level, the commit at which history of `object_names` is cut off, i.e.,
+/// `merge_base` if specified, or else their merge base.
+pub fn explain_merge_base<'a, Os>(merge_base: Option<&str>, object_names: Os) -> Result<()>
+where
+    Os: IntoIterator<Item = &'a str>,
+{
+    let merge_base = match merge_base {
+        Some(merge_base) => rev_parse(merge_base)?,
+        None => match self::merge_base(object_names)? {
+            Some(merge_base) => merge_base,
+            None => return Ok(()),
+        },
+    };
+    log::info!("history is cut off at merge base {merge_base}");
+    Ok(())
+}
+
+/// Find the merge commit on the first-parent history of `base` that merged `merged` into it, i.e.,
+/// the oldest such merge commit descending from `merged`.
+pub fn merge_commit_of(merged: &str, base: &str) -> Result<String> {
+    let merges = stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            cmd.args([
+                "rev-list",
+                "--merges",
+                "--first-parent",
+                "--ancestry-path",
+                "--end-of-options",
+            ])
+            .arg(format!("{merged}..{base}"))
+        }),
+        true,
+    )?;
+    let merge = merges.last().cloned().ok_or_else(|| {
+        Error::other(anyhow!(
+            "failed to find where {merged:?} was merged into {base:?}"
+        ))
+    })?;
+    log::debug!("{merged:?} was merged into {base:?} at {merge}");
+    Ok(merge)
+}
+
+/// Find the commit where the first-parent history of `head` meets `base`.
+///
+/// Unlike the octopus merge base used by default, this ignores commits reachable only through
+/// non-first parents of `head`'s history. When `base` has been merged into `head`, the merge
+/// base of the two is the merged commit of `base`, which hides commits of `head` that predate
+/// the merge. The first-parent merge base is, instead, where `head` originally forked from
+/// `base`.
+pub fn first_parent_merge_base(base: &str, head: &str) -> Result<String> {
+    let only_in_head = stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            cmd.args([
+                "rev-list",
+                "--first-parent",
+                "--topo-order",
+                "--end-of-options",
+            ])
+            .arg(format!("{base}..{head}"))
+        }),
+        true,
+    )?;
+    let fork = match only_in_head.last() {
+        Some(oldest) => format!("{oldest}^"),
+        None => head.to_owned(),
+    };
+    let merge_base = rev_parse(&fork)?;
+    log::debug!("first-parent merge base of {head:?} and {base:?}: {merge_base}");
+    Ok(merge_base)
+}
+
+pub fn list_branches_cmd(config: impl FnOnce(&mut Command) -> &mut Command) -> EasyCommand {
+    EasyCommand::new_with("git", |cmd| {
+        config(cmd.args(["branch", "--list", "--format=%(refname:short)"]))
+    })
+}
+
+/// Run `cmd`, capturing its `stdout` as lines.
+///
+/// If `trim` is set, surrounding whitespace is removed from each line. This is appropriate for
+/// output that's a list of names, like that of [`list_branches_cmd`], but not for general output
+/// (i.e., of `git log`) where leading whitespace can be significant.
+pub fn stdout_lines(mut cmd: EasyCommand, trim: bool) -> Result<Vec<String>> {
+    lines_of(cmd.output().map_err(Error::from_spawn)?, trim)
+}
+
+/// Like [`stdout_lines`] with `trim` set, but for commands that read refs, like that of
+/// [`list_branches_cmd`].
+///
+/// These can fail transiently while another Git process (i.e., `git gc` or `git pack-refs`)
+/// rewrites `packed-refs`, so such failures are retried a few times, with a short backoff, before
+/// giving up.
+pub fn ref_lines(mut cmd: EasyCommand) -> Result<Vec<String>> {
+    const RETRIES: u32 = 3;
+    let mut attempt = 0;
+    loop {
+        let output = cmd.output().map_err(Error::from_spawn)?;
+        if !output.status.success() && attempt < RETRIES && is_transient_ref_failure(&output.stderr)
+        {
+            let backoff = Duration::from_millis(50 << attempt);
+            log::debug!("{cmd} failed to read refs, retrying in {backoff:?}");
+            thread::sleep(backoff);
+            attempt += 1;
+            continue;
+        }
+        return lines_of(output, true);
+    }
+}
+
+/// Whether `stderr` of a failed command reports an error reading refs that's likely because they
+/// were being rewritten concurrently.
+fn is_transient_ref_failure(stderr: &[u8]) -> bool {
+    let stderr = String::from_utf8_lossy(stderr);
+    ["packed-refs", "unable to read", "cannot lock ref"]
+        .iter()
+        .any(|message| stderr.contains(message))
+}
+
+/// Capture `stdout` of `output` as lines, as for [`stdout_lines`], or relay `stderr` if it failed.
+fn lines_of(output: Output, trim: bool) -> Result<Vec<String>> {
+    let Output {
+        stdout,
+        stderr,
+        status,
+    } = output;
+
+    let status_res = Error::from_status(status);
+    if status_res.is_err() {
+        io::copy(&mut Cursor::new(stderr), &mut io::stderr()).unwrap();
+    }
+    status_res?;
+
+    let stdout = String::from_utf8(stdout)
+        .context("`stdout` was not UTF-8 (!?)")
+        .map_err(Error::other)?;
+    Ok(stdout
+        .lines()
+        .map(|line| if trim { line.trim() } else { line }.to_owned())
+        .collect())
+}
+
+pub fn git_config(path: &str) -> Result<Option<String>> {
+    let mut cmd = EasyCommand::new_with("git", |cmd| cmd.arg("config").arg(path));
+    let Some(mut lines) = git_config_lines(&mut cmd)? else {
+        return Ok(None);
+    };
+    let first_line = lines.next();
+    assert!(
+        lines.next().is_none(),
+        "{cmd} returned more than a single line of output"
+    );
+    Ok(first_line)
+}
+
+/// Like [`git_config`], but interprets the value as a boolean, like `true` or `off`.
+pub fn git_config_bool(path: &str) -> Result<Option<bool>> {
+    let mut cmd = EasyCommand::new_with("git", |cmd| {
+        cmd.args(["config", "--type=bool", "--get", path])
+    });
+    Ok(git_config_lines(&mut cmd)?
+        .and_then(|mut lines| lines.next())
+        .map(|value| value == "true"))
+}
+
+/// Like [`git_config`], but returns all values of a multi-valued configuration key.
+pub fn git_config_all(path: &str) -> Result<Vec<String>> {
+    let mut cmd = EasyCommand::new_with("git", |cmd| cmd.args(["config", "--get-all", path]));
+    Ok(git_config_lines(&mut cmd)?
+        .map(|lines| lines.collect())
+        .unwrap_or_default())
+}
+
+fn git_config_lines(cmd: &mut EasyCommand) -> Result<Option<impl Iterator<Item = String>>> {
+    let output = cmd.output().map_err(Error::from_spawn)?;
+    let Output {
+        stdout,
+        stderr,
+        status,
+    } = output;
+
+    match status.code() {
+        Some(0) => (),
+        Some(1) => return Ok(None),
+        _ => {
+            io::copy(&mut Cursor::new(stderr), &mut io::stderr()).unwrap();
+            return Err(Error::from_status(status).unwrap_err());
+        }
+    };
+
+    let stdout = String::from_utf8(stdout)
+        .context("`stdout` was not UTF-8 (!?)")
+        .map_err(Error::other)?;
+
+    let lines = stdout
+        .lines()
+        .map(|line| line.trim().to_owned())
+        .collect::<Vec<_>>();
+    log::trace!("`stdout` of {cmd}: {lines:?}");
+
+    Ok(Some(lines.into_iter()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit_decorated_with(decorations: &str) -> CommitInfo {
+        let fields = ["1234", "", "A", "a@b.c", "", "", decorations, "subject"];
+        CommitInfo::parse(&fields.join("\x1f")).unwrap()
+    }
+
+    #[test]
+    fn ref_names_of_branch_at_head_and_tags() {
+        let commit = commit_decorated_with("HEAD -> main, origin/main, tag: v2, tag: v2.0");
+        assert_eq!(
+            commit.ref_names(),
+            ["HEAD", "main", "origin/main", "v2", "v2.0"]
+        );
+    }
+
+    #[test]
+    fn ref_names_of_detached_head() {
+        let commit = commit_decorated_with("HEAD, tag: v1");
+        assert_eq!(commit.ref_names(), ["HEAD", "v1"]);
+        assert!(commit_decorated_with("").ref_names().is_empty());
+    }
+
+    fn preset(upstreams: bool, pushes: bool, last_tag: bool, remotes: bool) -> PresetConfig {
+        PresetConfig {
+            select_upstreams: upstreams,
+            select_pushes: pushes,
+            select_last_tag: last_tag,
+            select_remotes: remotes,
+        }
+    }
+
+    #[test]
+    fn branch_format_without_presets() {
+        let config = preset(false, false, false, false);
+        assert_eq!(branch_format(&config, false), "--format=%(refname:short)");
+        assert_eq!(
+            branch_format(&config, true),
+            "--format=%(if)%(HEAD)%(then)HEAD%(else)%(refname:short)%(end)"
+        );
+    }
+
+    #[test]
+    fn branch_format_with_upstreams() {
+        let config = preset(true, false, false, false);
+        assert_eq!(
+            branch_format(&config, false),
+            "--format=%(refname:short)%(if)%(upstream)%(then)\n%(upstream:short)%(end)"
+        );
+        assert_eq!(
+            branch_format(&config, true),
+            "--format=%(if)%(HEAD)%(then)HEAD%(else)%(refname:short)\
+            %(if)%(upstream)%(then)\n%(upstream:short)%(end)%(end)"
+        );
+    }
+
+    #[test]
+    fn branch_format_with_pushes() {
+        let config = preset(false, true, false, false);
+        assert_eq!(
+            branch_format(&config, false),
+            "--format=%(refname:short)%(if)%(push)%(then)\n%(push:short)%(end)"
+        );
+        assert_eq!(
+            branch_format(&config, true),
+            "--format=%(if)%(HEAD)%(then)HEAD%(else)%(refname:short)\
+            %(if)%(push)%(then)\n%(push:short)%(end)%(end)"
+        );
+    }
+
+    #[test]
+    fn branch_format_with_upstreams_and_pushes() {
+        let config = preset(true, true, false, false);
+        assert_eq!(
+            branch_format(&config, false),
+            "--format=%(refname:short)%(if)%(upstream)%(then)\n%(upstream:short)%(end)\
+            %(if)%(push)%(then)\n%(push:short)%(end)"
+        );
+    }
+
+    #[test]
+    fn branch_format_ignores_last_tag() {
+        for detached in [false, true] {
+            assert_eq!(
+                branch_format(&preset(false, false, true, false), detached),
+                branch_format(&preset(false, false, false, false), detached),
+            );
+        }
+    }
+
+    #[test]
+    fn branch_format_with_remotes() {
+        let config = preset(false, false, false, true);
+        assert_eq!(
+            branch_format(&config, false),
+            "--format=%(if)%(symref)%(then)%(else)%(refname:short)%(end)"
+        );
+        assert_eq!(
+            branch_format(&config, true),
+            "--format=%(if)%(symref)%(then)%(else)%(if)%(HEAD)%(then)HEAD%(else)\
+            %(refname:short)%(end)%(end)"
+        );
+    }
+
+    #[test]
+    fn branch_format_balances_ifs_for_every_preset() {
+        for bits in 0..(1 << 5) {
+            let bit = |n: u32| bits & (1 << n) != 0;
+            let config = preset(bit(0), bit(1), bit(2), bit(3));
+            let format = branch_format(&config, bit(4));
+            assert!(check_balanced_ifs(&format).is_ok(), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn mermaid_lanes_of_linear_history() {
+        let (lanes, tips, forks) = mermaid_lanes(&[vec![], vec![0], vec![1]]);
+        assert_eq!(lanes, [0, 0, 0]);
+        assert_eq!(tips, [2]);
+        assert!(forks.is_empty());
+    }
+
+    #[test]
+    fn mermaid_lanes_of_merged_fork() {
+        // `base`, then `main` and `feat` on top of it, then a merge of `feat` into `main`.
+        let (lanes, tips, forks) = mermaid_lanes(&[vec![], vec![0], vec![0], vec![1, 2]]);
+        assert_eq!(lanes, [0, 0, 1, 0]);
+        assert_eq!(tips, [3, 2]);
+        assert_eq!(forks, BTreeMap::from([(0, vec![1])]));
+    }
+
+    #[test]
+    fn mermaid_lanes_of_separate_roots() {
+        let (lanes, tips, forks) = mermaid_lanes(&[vec![], vec![], vec![1], vec![0, 2]]);
+        assert_eq!(lanes, [0, 1, 1, 0]);
+        assert_eq!(tips, [3, 2]);
+        assert!(forks.is_empty());
+    }
+
+    #[test]
+    fn autosquash_targets_match_like_git_rebase() {
+        let commits = [
+            ("e1", "e1", "newer"),
+            ("f4", "f4", "fixup! newer"),
+            ("f3", "f3", "fixup! fixup! add b"),
+            ("f2", "f2", "squash! c0ffee"),
+            ("f1", "f1", "amend! add"),
+            ("b", "b", "add b"),
+            ("c0ffee11", "c0f", "unrelated"),
+            ("a", "a", "add a"),
+        ];
+        let targets = autosquash_targets(&commits);
+        let targets = targets
+            .iter()
+            .map(|(fixup, target)| (fixup.as_str(), target.as_str()))
+            .collect::<Vec<_>>();
+        // Whole subjects match first, then object names, then the oldest subject beginning with
+        // what's left. Only older commits are targeted, so `f4` has no target.
+        assert_eq!(targets, [("f3", "b"), ("f2", "c0f"), ("f1", "a")]);
+    }
+
+    #[test]
+    fn autosquash_targets_prefer_whole_subjects() {
+        let commits = [
+            ("f", "f", "fixup! add"),
+            ("b", "b", "add"),
+            ("a", "a", "add a"),
+        ];
+        assert_eq!(
+            autosquash_targets(&commits),
+            [("f".to_owned(), "b".to_owned())]
+        );
+    }
+
+    #[test]
+    fn is_linear_by_parents_of_stacks() {
+        let shown = BTreeSet::from(["a", "b", "c"]);
+        // Parents outside of those shown, like `x`, don't count.
+        assert!(is_linear_by_parents(&shown, ["b", "c", "x"]));
+        assert!(is_linear_by_parents(&shown, ["b x", "c", ""]));
+        assert!(is_linear_by_parents(&BTreeSet::new(), []));
+    }
+
+    #[test]
+    fn is_linear_by_parents_of_forks_merges_and_roots() {
+        let shown = BTreeSet::from(["a", "b", "c"]);
+        assert!(!is_linear_by_parents(&shown, ["c", "c", ""]));
+        assert!(!is_linear_by_parents(&shown, ["b c", "c", ""]));
+        assert!(!is_linear_by_parents(&shown, ["b", "", ""]));
+    }
 }
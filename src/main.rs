@@ -1,25 +1,343 @@
-use std::{ffi::OsString, process::Command};
+use std::{
+    env,
+    ffi::OsString,
+    fs, io, iter,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
 
-use clap::Parser;
-use ezcmd::EasyCommand;
-use git_glimpse::{git_config, list_branches_cmd, run, show_graph, stdout_lines};
+use anyhow::{anyhow, Context};
+use clap::{builder::BoolishValueParser, CommandFactory, Parser, ValueEnum};
+use clap_complete::Shell;
+use git_glimpse::{
+    author_pattern_for_self, drop_missing, exclude_branches, explain_merge_base, git_config_all,
+    inactive_branches, merge_commit_of, release_ranges, resolve_base, resolve_render_config, run,
+    select_bisect, select_branches, select_from_log, select_refs, select_roots, select_stack,
+    select_stale, select_tags, show_digest, show_dot, show_graph, show_graph_json, show_mermaid,
+    show_tsv, show_upstream_divergence, sort_by_ahead, squash_merged_branches, stack_cutoff,
+    worktree_root, ColorChoice, GraphOptions, GraphStyle, OnBaseMode, OutputMode, PresetConfig,
+    RelativeLocale, ReleaseRange, StackOptions, TsvField,
+};
 
 /// Show a minimal graph of Git commits for various use cases.
 ///
 /// When no arguments are specified, this commands runs as if the `stack` command was invoked
 /// with no arguments.
 ///
-/// This binary has two optional points of Git configuration:
+/// This binary has several optional points of Git configuration:
 ///
 /// * `glimpse.base`: Sets the mainline branch. It is recommended that you use this only if
 ///   this command does not correctly detect your mainline branch out-of-the-box.
 ///
 /// * `glimpse.pretty`: The fallback value for the `--format` argument of this command.
+///
+/// * `glimpse.decorateRefsExclude`: A multi-valued set of patterns of refs to omit from commit
+///   decorations, like `refs/remotes/origin/pr/*`. See `git log --decorate-refs-exclude`.
+///
+/// * `glimpse.headMarker`: The marker used by `--mark-head`. Setting this also enables
+///   `--mark-head` by default.
+///
+/// * `glimpse.autoDecorate`: Whether to append decorations (`%d`) to custom formats without
+///   them. When unset, a warning is emitted for such formats instead.
+///
+/// * `glimpse.dashboard`: A multi-valued set of selections for the `dashboard` command.
+///
+/// * `glimpse.graphStyle`, `glimpse.color`, and `glimpse.pager`: Defaults for the
+///   `--graph-style`, `--color`, and `--paginate`/`--no-pager` flags, respectively.
+///
+/// * `glimpse.commitUrlTemplate`: A URL for commits, like
+///   `https://github.com/owner/repo/commit/%H`, with which to make object names in the output of
+///   custom formats into links, when output is colored and to a terminal. `%H` is replaced by the
+///   full object name of each commit.
+///
+/// * `glimpse.warnCommitThreshold`: The number of commits above which confirmation is asked for
+///   before showing them (see `--yes`).
 #[derive(Debug, Parser)]
 struct Args {
     /// Set the `--pretty` argument for underlying Git CLI calls.
+    ///
+    /// Falls back to the `glimpse.pretty` config, and then to a compact format showing one line
+    /// per commit. Use `--format=medium` for Git's own default.
     #[clap(long, short)]
     format: Option<String>,
+    /// When no format is specified or configured, omit authors and ages on narrow terminals.
+    ///
+    /// Terminals narrower than 100 columns, and output that isn't to a terminal, get a terse
+    /// format showing only each commit's abbreviated name, decorations, and subject.
+    #[clap(long)]
+    responsive_format: bool,
+    /// The columns of `--output tsv`, separated by commas.
+    #[clap(
+        long,
+        value_enum,
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        default_values_t = TsvField::DEFAULTS
+    )]
+    tsv_fields: Vec<TsvField>,
+    /// When no format is specified or configured, show committer dates instead of author dates.
+    ///
+    /// These diverge after a rebase, where the committer date is when commits landed.
+    #[clap(long, overrides_with = "use_author_date")]
+    use_committer_date: bool,
+    /// When no format is specified or configured, show author dates, which is the default.
+    #[clap(long, overrides_with = "use_committer_date")]
+    use_author_date: bool,
+    /// Truncate commit subjects to this many characters, leaving other fields intact.
+    ///
+    /// Truncated subjects end with an ellipsis. This requires a custom `--format` including `%s`.
+    #[clap(long, value_name = "N")]
+    truncate_subject: Option<usize>,
+    /// Show relative dates (i.e., `%ar` and `%cr`) in this language, rather than Git's English.
+    ///
+    /// These are computed from commits' timestamps, approximating Git's own rounding.
+    #[clap(long, value_enum, value_name = "LANG")]
+    relative_locale: Option<RelativeLocale>,
+    /// Mark commits without a `Signed-off-by` trailer, i.e., for DCO compliance, and print how
+    /// many there are after the graph.
+    #[clap(long)]
+    check_signoff: bool,
+    /// Mark merge commits that combine divergent history, whose parents aren't ancestors of one
+    /// another, unlike merges that could have been fast-forwards (i.e., with `git merge
+    /// --no-ff`).
+    #[clap(long)]
+    no_ff_only_merges: bool,
+    /// Mark commits that aren't reachable from any remote-tracking branch with `local`, i.e., to
+    /// review what pushing would publish.
+    #[clap(long)]
+    mark_unpushed: bool,
+    /// Label each commit with its position in the stack, counting from the merge base, like
+    /// `[3/7]`.
+    ///
+    /// Positions are unambiguous only for linear history. Otherwise, commits are numbered in
+    /// topological order, and a warning is emitted.
+    #[clap(long)]
+    stack_position: bool,
+    /// Label each `fixup!`, `squash!`, and `amend!` commit with the commit that
+    /// `git rebase --autosquash` would squash it into, like `→ 1a2b3c4`.
+    #[clap(long)]
+    resolve_fixups: bool,
+    /// Show uncommitted changes in the working tree, if any, as a line above `HEAD`.
+    #[clap(long)]
+    wip: bool,
+    /// When run from a terminal, show the most recent commits at `HEAD` right away, replacing
+    /// them with the full graph once it's ready.
+    ///
+    /// This helps in large repositories, where finding the merge base can take a while.
+    #[clap(long)]
+    progressive: bool,
+    /// How to print the selected commits. All modes but `text` print the selected commits
+    /// instead of a graph, and ignore display options.
+    ///
+    /// `json` prints an array of objects with each commit's `hash`, `parents`, `refs`,
+    /// `subject`, `author`, and `committed_at` date.
+    ///
+    /// `dot` prints a Graphviz digraph, i.e., for rendering an SVG with `dot -Tsvg`, with commits
+    /// labeled with their decorations and subjects.
+    ///
+    /// `mermaid` prints a Mermaid `gitGraph` diagram, i.e., for embedding in Markdown. Mermaid
+    /// can't draw all histories faithfully, so a warning is emitted for those it can't.
+    ///
+    /// `tsv` prints tab-separated values with a header row, i.e., for pasting into a spreadsheet
+    /// (see `--tsv-fields`).
+    ///
+    /// `digest` prints a SHA-256 digest of the selected commits and their parents. It doesn't
+    /// depend on display options, so scripts can compare it with an earlier one to cheaply detect
+    /// whether a selection has changed.
+    #[clap(long, value_enum, value_name = "MODE", default_value_t)]
+    output: OutputMode,
+    /// Drop selected refs that don't resolve to commits with a warning, rather than failing, i.e.,
+    /// when refs listed by another tool have since been deleted.
+    ///
+    /// This still fails if none of the selected refs resolve.
+    #[clap(long)]
+    ignore_missing: bool,
+    /// Instead of a graph, print each source consulted for the base branch and the format, in
+    /// order of precedence, with its value, marking the one that wins with `*`.
+    #[clap(long)]
+    show_config_resolution: bool,
+    /// Show signature verification status of each commit (see `git log --show-signature`).
+    ///
+    /// Verifying signatures can be slow for large selections, so this is off by default.
+    #[clap(long)]
+    show_signature: bool,
+    /// Show the abbreviated object names of each commit's parents, which helps when the graph
+    /// is ambiguous.
+    #[clap(long)]
+    show_parents: bool,
+    /// Decorate commits with all refs, ignoring the `glimpse.decorateRefsExclude` config.
+    #[clap(long)]
+    decorate_all: bool,
+    /// Prefix the line of the commit at `HEAD` with a marker (`>` by default).
+    #[clap(long)]
+    mark_head: bool,
+    /// Prefix the line of the merge base of all selected refs, below which history is cut off,
+    /// with a `base` marker.
+    #[clap(long, alias = "show-base")]
+    mark_base: bool,
+    /// Disable all of this command's embellishments of Git's output, like `--mark-head`, even if
+    /// they are enabled via configuration.
+    #[clap(long)]
+    plain: bool,
+    /// Browse the graph in a TUI, where selecting a commit shows it in full.
+    #[cfg(feature = "interactive")]
+    #[clap(long, short)]
+    interactive: bool,
+    /// Never access the network.
+    ///
+    /// All refs this command selects, including `@{upstream}` and `@{push}` counterparts, are
+    /// resolved from local refs, so this command only touches the network when Git itself does.
+    /// In a partial clone, Git lazily fetches objects missing from the local repository; this flag
+    /// disables that (via `GIT_NO_LAZY_FETCH`), so such operations fail fast instead.
+    #[clap(long, env = "GIT_GLIMPSE_OFFLINE", value_parser = BoolishValueParser::new())]
+    offline: bool,
+    /// Ignore `git replace` refs, showing history as it was actually committed.
+    ///
+    /// By default, Git honors replacements (i.e., grafts) when finding merge bases and walking
+    /// history, which gives the most accurate graph of a repository that uses them. This disables
+    /// them for every Git call (via `GIT_NO_REPLACE_OBJECTS`), like `git --no-replace-objects`.
+    #[clap(long)]
+    no_replace_objects: bool,
+    /// Only show history newer than this commit, instead of cutting it off at the merge base of
+    /// all selected refs.
+    ///
+    /// This skips the merge base computation, and shows commits that aren't descendants of this
+    /// commit, too.
+    #[clap(long, value_name = "COMMIT")]
+    since_ref: Option<String>,
+    /// Only show history newer than the merge of this ref into the base branch, like for release
+    /// notes since a release branch was merged.
+    ///
+    /// The base branch is configured as for `stack --base`. The merge is the oldest merge commit
+    /// in the first-parent history of the base branch that descends from this ref.
+    #[clap(long, value_name = "REF", conflicts_with = "since_ref")]
+    since_merge: Option<String>,
+    /// Color author names and emails consistently per author, to make it easier to tell who did
+    /// what.
+    ///
+    /// This requires a custom `--format` that includes author names (`%an`) or emails (`%ae`),
+    /// and only takes effect when output is colored.
+    #[clap(long)]
+    color_by_author: bool,
+    /// Print a key explaining the markers and other embellishments in use before the graph.
+    ///
+    /// This can also be enabled with the `glimpse.legend` config, which `--plain` overrides.
+    #[clap(long)]
+    legend: bool,
+    /// Only show commits that touch all of the specified files, rather than any of them.
+    ///
+    /// Git can't do this by itself, so this runs Git once per file to find such commits, which
+    /// is slower for many files. Matching commits are listed without the history between them,
+    /// and hence without a graph (see `git log --no-walk`).
+    #[clap(long)]
+    all_paths: bool,
+    /// Only show commits authored by you, according to the `user.email` config.
+    #[clap(long)]
+    mine: bool,
+    /// Hide commits whose author name or email matches this basic regular expression, like
+    /// `'\[bot\]'`.
+    ///
+    /// This can be specified multiple times. Git can't exclude authors by itself, so this runs
+    /// Git beforehand to find the commits to show. Like `--all-paths`, they're listed without the
+    /// history between them, and hence without a graph.
+    #[clap(long, value_name = "PATTERN")]
+    exclude_author: Vec<String>,
+    /// Re-encode commit messages into this encoding (see `git log --encoding`). Defaults to
+    /// UTF-8.
+    #[clap(long)]
+    encoding: Option<String>,
+    /// Stop walking history this many commits from each selected ref.
+    ///
+    /// This is a guardrail against graphing enormous amounts of history when refs are selected
+    /// by mistake, like with a bad `--base`. Depth is approximated by counting commits in the
+    /// same order as `git rev-list --max-count`. Note that selected refs whose commits are
+    /// ancestors of pruned history are hidden, too.
+    #[clap(long, value_name = "N")]
+    graph_max_depth: Option<usize>,
+    /// Show at most this many commits, newest first (see `git log --max-count`).
+    ///
+    /// Unlike `--graph-max-depth`, this doesn't change which commits are selected, nor the merge
+    /// base at which history is cut off; the graph is just truncated.
+    #[clap(long, short = 'n', value_name = "N", global = true)]
+    max_count: Option<usize>,
+    /// Dim commits committed longer ago than this, like `30d`, to focus on recent activity.
+    ///
+    /// Durations are a number followed by a unit of `s`, `m`, `h`, `d`, or `w`. This only takes
+    /// effect when output is colored, and requires a custom `--format`.
+    #[clap(long, value_name = "DURATION", value_parser = parse_age)]
+    dim_older_than: Option<Duration>,
+    /// Hide commits committed longer ago than this, like `30d` (see `--dim-older-than`).
+    #[clap(long, value_name = "DURATION", value_parser = parse_age)]
+    hide_older_than: Option<Duration>,
+    /// Only show commits committed after this date, like `2 weeks ago` or `2024-01-31` (see `git
+    /// log --since`).
+    ///
+    /// The merge base is still computed from all selected refs, so refs without commits in this
+    /// range contribute nothing to the graph, rather than failing.
+    #[clap(long, value_name = "DATE", global = true)]
+    since: Option<String>,
+    /// Only show commits committed before this date (see `--since` and `git log --until`).
+    #[clap(long, value_name = "DATE", global = true)]
+    until: Option<String>,
+    /// The characters with which to draw the graph. Falls back to the `glimpse.graphStyle` config,
+    /// and then to `ascii`.
+    #[clap(long, value_enum)]
+    graph_style: Option<GraphStyle>,
+    /// When to color output. Falls back to the `glimpse.color` config, and then to `auto`.
+    ///
+    /// `auto` colors according to Git's `color.*` configuration, but only when output is a
+    /// terminal, so that piping output yields plain text.
+    #[clap(long, value_enum, value_name = "WHEN")]
+    color: Option<ColorChoice>,
+    /// Don't color decorations, while still coloring the rest of each commit's line.
+    ///
+    /// This is useful when decoration colors clash with those of a custom `--format`.
+    #[clap(long)]
+    no_color_decorate: bool,
+    /// Override configuration for every Git call, like `core.abbrev=12`, as with `git -c`.
+    ///
+    /// This can be specified multiple times, and also applies to `glimpse.*` configs.
+    #[clap(
+        long = "git-config",
+        short = 'c',
+        value_name = "KEY=VALUE",
+        value_parser = parse_config_override
+    )]
+    git_config: Vec<(String, String)>,
+    /// Load `glimpse.*` configs from this TOML file, rather than from `.glimpse.toml` at the root
+    /// of the working tree, if it exists.
+    ///
+    /// Top-level keys name configs without the `glimpse.` prefix, like `base = "main"` or
+    /// `pretty = "%h %s"`, and arrays specify multiple values. These take precedence over Git's
+    /// own configuration, but not over `--git-config` or `GIT_CONFIG_*` environment variables.
+    #[clap(long, value_name = "PATH")]
+    config_file: Option<PathBuf>,
+    /// Page output, even if the `glimpse.pager` config disables paging.
+    #[clap(long, overrides_with = "no_pager")]
+    paginate: bool,
+    /// Don't page output. Paging can also be disabled with the `glimpse.pager` config.
+    #[clap(long, overrides_with = "paginate")]
+    no_pager: bool,
+    /// Don't ask for confirmation before showing a large number of commits.
+    ///
+    /// When run from a terminal, confirmation is asked for if more commits would be shown than
+    /// the `glimpse.warnCommitThreshold` config allows, which defaults to 1000. Set it to 0 to
+    /// never ask.
+    #[clap(long, short)]
+    yes: bool,
+    /// Exit with code 3 instead of showing a graph if there are no commits besides the merge
+    /// base, i.e., if the current branch has no commits of its own.
+    ///
+    /// Otherwise, an empty graph is shown like any other, exiting with code 0. This is handy for
+    /// scripts and CI.
+    #[clap(long)]
+    require_nonempty: bool,
+    /// Only log errors, silencing warnings and informational messages, i.e., for scripts.
+    ///
+    /// This takes precedence over `RUST_LOG`.
+    #[clap(long, short)]
+    quiet: bool,
     #[clap(subcommand)]
     subcommand: Option<Subcommand>,
 }
@@ -32,8 +350,43 @@ enum Subcommand {
     /// branch. This is useful for day-to-day work, where you want only the commits relevant to
     /// what you're currently working on.
     Stack {
+        /// The mainline branch. Falls back to the `glimpse.base` config, then to the upstream of
+        /// the current branch (i.e., `develop` if it tracks `origin/develop`), and then to `main`.
         #[clap(long, short)]
         base: Option<String>,
+        /// Cut off history where the first-parent line of `HEAD` meets the base branch.
+        ///
+        /// By default, history is cut off at the merge base of all selected branches. If the base
+        /// branch has been merged into the current branch, that merge base is the most recently
+        /// merged commit of the base branch, which hides commits on the current branch from
+        /// before that merge. This option instead follows only the first parents of `HEAD`, so
+        /// the cutoff stays where the current branch originally forked from the base branch.
+        #[clap(long)]
+        first_parent_base: bool,
+        /// Instead of showing a graph, preview squashing the current branch into a single commit.
+        ///
+        /// This prints the combined diffstat of commits on the current branch since it forked
+        /// from the base branch, followed by a suggested commit message listing their subjects.
+        #[clap(long)]
+        squash_preview: bool,
+        /// Explain how the base branch was chosen, and where history is cut off.
+        ///
+        /// This lists each candidate for the base branch in order of precedence (`--base`, then
+        /// the `glimpse.base` config, then the upstream of the current branch, then `main`),
+        /// whether it exists, and the merge base of the selected branches.
+        #[clap(long)]
+        explain_base: bool,
+        /// What to select when the current branch is the base branch itself.
+        ///
+        /// `upstream` selects the base branch and its upstream, to see what's unpushed and
+        /// unpulled. `recent` and `merges` select only the base branch, showing its most recent
+        /// commits or merges (see `--on-base-count`), respectively.
+        #[clap(long, value_enum, default_value_t)]
+        on_base_mode: OnBaseMode,
+        /// How many of the most recent commits or merges of the base branch to show for
+        /// `--on-base-mode`.
+        #[clap(long, value_name = "N", default_value_t = 10)]
+        on_base_count: usize,
         #[clap(flatten)]
         config: PresetConfig,
         #[clap(flatten)]
@@ -51,6 +404,180 @@ enum Subcommand {
     Locals {
         #[clap(flatten)]
         config: PresetConfig,
+        /// The order in which to select branches, which breaks ties in the layout of the graph.
+        ///
+        /// `-ahead` sorts by how many commits each branch is ahead of the base branch (see `stack
+        /// --base`), which runs Git once per branch.
+        #[clap(long, value_enum, allow_hyphen_values = true)]
+        sort: Option<RefSort>,
+        /// Fail if more than this many refs are selected. `0` disables this limit.
+        ///
+        /// Graphing a very large number of refs is slow, so this guards scripts against repositories
+        /// with unexpectedly many of them. Refs too long to pass on a command line are passed to
+        /// Git via `--stdin` instead, so they never need to be limited for that.
+        #[clap(long, default_value_t = 500)]
+        max_refs: usize,
+        /// Only select branches that contain this commit (see `git branch --contains`).
+        ///
+        /// This is useful for checking which of your branches already have a fix, for example.
+        #[clap(long, value_name = "COMMIT")]
+        contains: Vec<String>,
+        /// Only select branches that don't contain this commit (see `git branch --no-contains`).
+        #[clap(long, value_name = "COMMIT")]
+        no_contains: Vec<String>,
+        /// Only select branches whose tip is this object (see `git branch --points-at`).
+        #[clap(long, value_name = "OBJECT")]
+        points_at: Option<String>,
+        /// Omit branches that are already merged into this ref, like `origin/main` (see
+        /// `git branch --no-merged`).
+        #[clap(long, value_name = "REF")]
+        exclude_merged_into: Option<String>,
+        /// With `--exclude-merged-into`, also omit branches whose changes have landed via a
+        /// squash merge or cherry-picks.
+        #[clap(long, requires = "exclude_merged_into")]
+        detect_squashed: bool,
+        /// Only select branches with a commit more recent than this, 14 days by default (see
+        /// `--dim-older-than`).
+        ///
+        /// This cuts through stale branches without deleting them (see also `stale`).
+        #[clap(
+            long,
+            value_name = "DURATION",
+            value_parser = parse_age,
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "14d"
+        )]
+        active: Option<Duration>,
+        /// Omit branches whose names match this glob pattern, like `wip/*` or `dependabot/*`, in
+        /// addition to those of the multi-valued `glimpse.exclude` config.
+        ///
+        /// `*` matches any characters, including `/`, and `?` matches any single character.
+        #[clap(long, value_name = "PATTERN")]
+        exclude: Vec<String>,
+        /// Instead of a graph, list each branch followed by how far it has diverged from its
+        /// upstream, so that the two don't end up far apart from each other.
+        #[clap(long)]
+        interleave: bool,
+        #[clap(flatten)]
+        files: FileSelection,
+    },
+    /// Select local branches that are fully merged into the base branch, and have no recent
+    /// commits, as candidates for deletion.
+    ///
+    /// The base branch is selected along with them, and a `git branch -d` command for each stale
+    /// branch is printed after the graph. Nothing is ever deleted by this command.
+    Stale {
+        /// The mainline branch. Falls back to the `glimpse.base` config, then to the upstream of
+        /// the current branch (i.e., `develop` if it tracks `origin/develop`), and then to `main`.
+        #[clap(long, short)]
+        base: Option<String>,
+        /// Only select branches whose tips were committed longer ago than this (see
+        /// `--dim-older-than`).
+        #[clap(
+            long,
+            value_name = "DURATION",
+            value_parser = parse_age,
+            default_value = "90d"
+        )]
+        older_than: Duration,
+        #[clap(flatten)]
+        files: FileSelection,
+    },
+    /// Select the remaining range of commits of an ongoing `git bisect` session.
+    ///
+    /// Known-bad, known-good and skipped commits are marked in red, green and yellow, respectively.
+    Bisect {
+        #[clap(flatten)]
+        files: FileSelection,
+    },
+    /// Select tags, i.e., to find which releases include a commit.
+    Tags {
+        /// Only select tags that contain this commit (see `git tag --contains`).
+        #[clap(long, value_name = "COMMIT")]
+        contains: Vec<String>,
+        /// Only select tags reachable from this ref (see `git tag --merged`).
+        #[clap(long, value_name = "REF")]
+        merged: Option<String>,
+        #[clap(flatten)]
+        files: FileSelection,
+    },
+    /// Show the commits of each release, i.e., between each pair of adjacent tags, newest first.
+    ///
+    /// Each range is headed by the tags it's between and how many commits it has, like a
+    /// changelog. Structured outputs (see `--output`) aren't supported.
+    Releases {
+        /// The pattern of tags of releases (see `git tag --list`). Tags are sorted as versions,
+        /// like with `git tag --sort=version:refname`.
+        #[clap(default_value = "v*")]
+        pattern: String,
+        /// Only show this many of the newest release ranges.
+        #[clap(long, value_name = "N")]
+        limit: Option<usize>,
+        #[clap(flatten)]
+        files: FileSelection,
+    },
+    /// Select refs in a custom namespace, like Gerrit's changes under `refs/changes/`.
+    ///
+    /// This is useful for code review tools that keep refs outside of `refs/heads/` and
+    /// `refs/tags/`.
+    Refs {
+        /// Patterns of refs to select (see `git for-each-ref`). Patterns without wildcards match
+        /// all refs under them, like `refs/changes/`.
+        #[clap(required = true)]
+        patterns: Vec<String>,
+        /// Fail if more than this many refs are selected (see `locals --max-refs`).
+        #[clap(long, default_value_t = 500)]
+        max_refs: usize,
+        #[clap(flatten)]
+        files: FileSelection,
+    },
+    /// Select the earliest history of the repository, starting from its root commits.
+    ///
+    /// Repositories can have multiple root commits, i.e., when unrelated histories have been
+    /// merged. The first commits of each are selected.
+    Root {
+        /// The number of commits to select from each root commit, including the root commit.
+        ///
+        /// Unlike `--max-count` (i.e., `-n`), this is counted for each root commit separately.
+        #[clap(long, default_value_t = 10)]
+        count: usize,
+        /// Find root commits reachable from any ref, rather than only from `HEAD`.
+        #[clap(long)]
+        all: bool,
+        #[clap(flatten)]
+        files: FileSelection,
+    },
+    /// Select commits whose messages match a pattern, along with their parents, i.e., to find
+    /// where a fix for an advisory landed without knowing which refs contain it.
+    SelectFromLog {
+        /// The basic regular expression to match commit messages against (see
+        /// `git log --grep`).
+        pattern: String,
+        /// Only search the history of these commits, rather than that of all refs.
+        #[clap(long = "in", value_name = "REV")]
+        tips: Vec<String>,
+        /// Match the pattern case-insensitively.
+        #[clap(long, short = 'i')]
+        regexp_ignore_case: bool,
+        #[clap(flatten)]
+        files: FileSelection,
+    },
+    /// Show several selections at once, each preceded by a header.
+    ///
+    /// Selections are configured with the multi-valued `glimpse.dashboard` config, each of whose
+    /// values is a subcommand and its arguments, like `stack --upstreams`. Defaults to `stack`
+    /// followed by `locals`. Options specified before this subcommand apply to every selection.
+    Dashboard,
+    /// Select the given refs, or, if none are given, all local branches along with their upstream
+    /// and push counterparts.
+    ///
+    /// This is equivalent to `locals --upstreams --pushes` when no refs are given, and to
+    /// `select` otherwise. It's handy as a Git alias: `git config --global alias.graph 'glimpse
+    /// graph'`.
+    Graph {
+        /// Refs to select instead of local branches.
+        branches: Vec<String>,
         #[clap(flatten)]
         files: FileSelection,
     },
@@ -58,22 +585,48 @@ enum Subcommand {
     Select {
         /// Additional branches to include.
         branches: Vec<String>,
+        /// Instead of showing a graph, compare two versions of a branch, i.e., from before and
+        /// after a rebase, with `git range-diff BASE..OLD BASE..NEW`.
+        #[clap(long, num_args = 2, value_names = ["OLD", "NEW"], conflicts_with = "branches")]
+        range_diff: Option<Vec<String>>,
+        /// The mainline branch for `--range-diff`, resolved as for `stack --base`.
+        #[clap(long, short, requires = "range_diff")]
+        base: Option<String>,
+        /// Forwarded to `git range-diff --creation-factor`.
+        #[clap(long, value_name = "PERCENT", requires = "range_diff")]
+        creation_factor: Option<u32>,
         #[clap(flatten)]
         files: FileSelection,
     },
+    /// Print a completion script for a shell, like `git-glimpse completions bash >
+    /// /etc/bash_completion.d/git-glimpse`.
+    ///
+    /// This doesn't require a Git repository.
+    Completions {
+        /// The shell to complete commands of.
+        shell: Shell,
+    },
+    /// Print a single field of a single commit, like the subject of `HEAD`, for scripting.
+    ///
+    /// This is shorthand for `git log --max-count=1 --format=PLACEHOLDER REV`.
+    ShowField {
+        /// The commit to show a field of.
+        rev: String,
+        /// The `--format` placeholder of the field to show, like `%s` or `%an`.
+        placeholder: String,
+    },
 }
 
-#[derive(Debug, Parser)]
-struct PresetConfig {
-    /// Also include all `@{upstream}` counterparts to selected branches.
-    #[clap(long = "upstreams", short = 'u')]
-    select_upstreams: bool,
-    /// Also select all `@{push}` counterparts to selected branches.
-    #[clap(long = "pushes", short = 'p')]
-    select_pushes: bool,
-    /// Also select the last tag that contains `HEAD`.
-    #[clap(long = "last-tag")]
-    select_last_tag: bool,
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum RefSort {
+    /// By name.
+    Name,
+    /// By committer date, most recent first.
+    #[value(name = "-committerdate")]
+    CommitterDate,
+    /// By how many commits ahead of the base branch, most first.
+    #[value(name = "-ahead")]
+    Ahead,
 }
 
 #[derive(Debug, Parser)]
@@ -85,119 +638,705 @@ struct FileSelection {
 
 fn main() {
     run(|| {
-        let Args { format, subcommand } = Args::parse();
-        let subcommand = subcommand.unwrap_or_else(|| Subcommand::Stack {
+        let mut args = Args::parse();
+        // This shouldn't depend on Git, so skip any setup that runs it.
+        if let Some(Subcommand::Completions { shell }) = args.subcommand {
+            print_completions(shell);
+            return Ok(());
+        }
+        if args.quiet {
+            log::set_max_level(log::LevelFilter::Error);
+        }
+        if args.offline {
+            log::debug!("offline mode enabled, disabling lazy fetching of missing objects");
+            env::set_var("GIT_NO_LAZY_FETCH", "1");
+        }
+        if args.no_replace_objects {
+            env::set_var("GIT_NO_REPLACE_OBJECTS", "1");
+        }
+        underlay_git_config(&load_config_file(args.config_file.as_deref())?);
+        override_git_config(&args.git_config);
+        let subcommand = args.subcommand.take().unwrap_or_else(|| Subcommand::Stack {
             base: None,
-            config: PresetConfig {
-                select_upstreams: false,
-                select_pushes: false,
-                select_last_tag: false,
-            },
+            first_parent_base: false,
+            squash_preview: false,
+            explain_base: false,
+            on_base_mode: OnBaseMode::default(),
+            on_base_count: 10,
+            config: PresetConfig::default(),
             files: FileSelection { files: vec![] },
         });
-        let current_branch = || {
-            stdout_lines(EasyCommand::new_with("git", |cmd| {
-                cmd.args(["branch", "--show-current"])
-            }))
-            .map(|mut lines| {
-                let current_branch = lines.pop();
-                log::trace!("current branch: {current_branch:?}");
-                log::trace!("`HEAD` is detached: {:?}", current_branch.is_some());
-                debug_assert!(lines.is_empty());
-                current_branch
-            })
+        show(&args, subcommand)
+    })
+}
+
+fn show(args: &Args, subcommand: Subcommand) -> git_glimpse::Result<()> {
+    let Args {
+        format,
+        responsive_format,
+        tsv_fields,
+        use_committer_date,
+        use_author_date: _,
+        truncate_subject,
+        relative_locale,
+        check_signoff,
+        no_ff_only_merges,
+        mark_unpushed,
+        stack_position,
+        resolve_fixups,
+        wip,
+        progressive,
+        output,
+        ignore_missing,
+        show_config_resolution,
+        show_signature,
+        show_parents,
+        decorate_all,
+        mark_head,
+        mark_base,
+        plain,
+        #[cfg(feature = "interactive")]
+        interactive,
+        offline: _,
+        no_replace_objects: _,
+        since_ref,
+        since_merge,
+        color_by_author,
+        legend,
+        all_paths,
+        mine,
+        exclude_author,
+        encoding,
+        graph_max_depth,
+        max_count,
+        dim_older_than,
+        hide_older_than,
+        since: since_date,
+        until: until_date,
+        graph_style,
+        color,
+        no_color_decorate,
+        git_config: _,
+        config_file: _,
+        paginate,
+        no_pager,
+        yes,
+        require_nonempty,
+        quiet: _,
+        subcommand: _,
+    } = args;
+    let mut merge_base = None;
+    let mut markers = Vec::new();
+    let mut since = None;
+    let mut decorate_refs = Vec::new();
+    let mut epilogue = None;
+    let mut releases = None;
+    if *show_config_resolution {
+        let base = match &subcommand {
+            Subcommand::Stack { base, .. } | Subcommand::Stale { base, .. } => base.as_deref(),
+            _ => None,
         };
-        let branches = |sel_config: &_,
-                        cmd_config: &dyn Fn(&mut Command) -> &mut Command|
-         -> git_glimpse::Result<_> {
-            let PresetConfig {
-                select_upstreams,
-                select_pushes,
-                select_last_tag,
-            } = sel_config;
-            let head_is_detached = current_branch()?.is_none();
-
-            let mut format = "--format=".to_owned();
-            if head_is_detached {
-                format.push_str("%(if)%(HEAD)%(then)HEAD%(else)");
+        return git_glimpse::show_config_resolution(
+            base,
+            format.as_deref(),
+            *responsive_format,
+            *use_committer_date,
+        );
+    }
+    let (branches, files) = match subcommand {
+        Subcommand::Dashboard => return show_dashboard(args),
+        Subcommand::Completions { shell } => {
+            print_completions(shell);
+            return Ok(());
+        }
+        Subcommand::ShowField { rev, placeholder } => {
+            return git_glimpse::show_field(&rev, &placeholder)
+        }
+        Subcommand::Stack {
+            base,
+            first_parent_base,
+            squash_preview,
+            explain_base,
+            on_base_mode,
+            on_base_count,
+            config,
+            files: FileSelection { files },
+        } => {
+            if explain_base {
+                git_glimpse::explain_base(base.as_deref())?;
             }
-            format.push_str("%(refname:short)");
-            let mut include_in_format = |prop_name: &str| {
-                format += &format!("%(if)%({prop_name})%(then)\n%({prop_name}:short)%(end)");
+            let base = resolve_base(base)?;
+            let options = StackOptions {
+                base: Some(base.clone()),
+                config,
+                first_parent_base,
+                on_base: on_base_mode,
+                on_base_count,
             };
-            if *select_upstreams {
-                include_in_format("upstream");
+            let (stack_merge_base, on_base_since) = stack_cutoff(&options)?;
+            merge_base = stack_merge_base;
+            if squash_preview {
+                return git_glimpse::squash_preview(merge_base.as_deref().unwrap_or(&base));
             }
-            if *select_pushes {
-                include_in_format("push");
+            // Explicitly cutting off history takes precedence over doing so for the base branch.
+            if since_ref.is_none() && since_merge.is_none() {
+                since = on_base_since;
             }
-            if head_is_detached {
-                format.push_str("%(end)");
+            let branches = select_stack(&options)?;
+            if explain_base {
+                explain_merge_base(
+                    merge_base.as_deref(),
+                    branches.iter().map(|branch| branch.as_str()),
+                )?;
             }
-
-            let mut branches = stdout_lines(list_branches_cmd(|cmd| cmd_config(cmd.arg(format))))?;
-
-            if *select_last_tag {
-                match stdout_lines(EasyCommand::new_with("git", |cmd| {
-                    cmd.args(["rev-list", "--tags", "--max-count=1"])
-                }))?
-                .pop()
-                {
-                    Some(last_tag) => branches.push(last_tag),
-                    None => log::warn!("last tag requested, but no last tag was found"),
+            (branches, files)
+        }
+        Subcommand::Locals {
+            config,
+            sort,
+            max_refs,
+            contains,
+            no_contains,
+            points_at,
+            exclude_merged_into,
+            detect_squashed,
+            active,
+            exclude,
+            interleave,
+            files: FileSelection { files },
+        } => {
+            let filter: &dyn Fn(&mut Command) -> &mut Command = &|cmd| {
+                for commit in &contains {
+                    cmd.arg(format!("--contains={commit}"));
+                }
+                for commit in &no_contains {
+                    cmd.arg(format!("--no-contains={commit}"));
+                }
+                if let Some(object) = &points_at {
+                    cmd.arg(format!("--points-at={object}"));
                 }
+                if let Some(upstream) = &exclude_merged_into {
+                    cmd.arg(format!("--no-merged={upstream}"));
+                }
+                match sort {
+                    Some(RefSort::Name) => cmd.arg("--sort=refname"),
+                    Some(RefSort::CommitterDate) => cmd.arg("--sort=-committerdate"),
+                    Some(RefSort::Ahead) | None => cmd,
+                }
+            };
+            let exclude = exclude
+                .into_iter()
+                .chain(git_config_all("glimpse.exclude")?)
+                .collect::<Vec<_>>();
+            if interleave {
+                return show_upstream_divergence(filter, &exclude);
             }
-
-            Ok(branches)
-        };
-        let (branches, files) = match subcommand {
-            Subcommand::Stack {
-                base,
-                config,
-                files: FileSelection { files },
-            } => {
-                let specified_base = base
-                    .map(Ok)
-                    .or_else(|| git_config("glimpse.base").transpose())
-                    .transpose()?;
-                let base = specified_base.as_deref().unwrap_or_else(|| {
-                    let default = "main";
-                    log::debug!("no base branch specified in command line or configuration, falling back to {default:?}");
-                    default
-                });
-
-                let branches = if let Some(current_branch) = current_branch()? {
-                    let mut config = config;
-                    if current_branch == base {
-                        config.select_upstreams = true;
-                    }
-                    branches(&config, &|cmd| {
-                        if base != current_branch {
-                            cmd.arg(base);
-                        }
-                        cmd.arg(&current_branch).arg("--all")
-                    })?
-                } else {
-                    let mut branches = branches(&config, &|cmd| cmd.arg(base).arg("--all"))?;
-                    branches.push("HEAD".to_owned());
+            let branches = select_branches(&config, filter)?;
+            let selected = branches.len();
+            let branches = exclude_branches(branches, &exclude);
+            if branches.is_empty() && selected > 0 {
+                log::info!("all branches were excluded");
+                return Ok(());
+            }
+            let branches = match exclude_merged_into.filter(|_| detect_squashed) {
+                Some(upstream) => {
+                    let squashed = squash_merged_branches(&upstream)?;
                     branches
+                        .into_iter()
+                        .filter(|branch| !squashed.contains(branch))
+                        .collect()
+                }
+                None => branches,
+            };
+            let mut branches = match active {
+                Some(active_within) => {
+                    // Upstreams and pushes are usually remote-tracking branches, too.
+                    let remotes =
+                        config.select_remotes || config.select_upstreams || config.select_pushes;
+                    let inactive = inactive_branches(active_within, remotes)?;
+                    let active = branches
+                        .into_iter()
+                        .filter(|branch| !inactive.contains(branch))
+                        .collect::<Vec<_>>();
+                    if active.is_empty() {
+                        log::info!("no branches have been committed to recently");
+                        return Ok(());
+                    }
+                    active
+                }
+                None => branches,
+            };
+            check_max_refs(&branches, max_refs)?;
+            if let Some(RefSort::Ahead) = sort {
+                sort_by_ahead(&mut branches, &resolve_base(None)?)?;
+            }
+            (branches, files)
+        }
+        Subcommand::Stale {
+            base,
+            older_than,
+            files: FileSelection { files },
+        } => {
+            let base = resolve_base(base)?;
+            let mut branches = select_stale(&base, older_than)?;
+            if branches.is_empty() {
+                log::info!("no branches merged into {base:?} are stale");
+                return Ok(());
+            }
+            epilogue = Some(
+                branches
+                    .iter()
+                    .map(|branch| format!("git branch -d {}\n", shell_words::quote(branch)))
+                    .collect::<String>(),
+            );
+            branches.push(base);
+            (branches, files)
+        }
+        Subcommand::Bisect {
+            files: FileSelection { files },
+        } => {
+            let (branches, boundary, bisect_markers) = select_bisect()?;
+            since = Some(boundary);
+            markers = bisect_markers;
+            (branches, files)
+        }
+        Subcommand::Tags {
+            contains,
+            merged,
+            files: FileSelection { files },
+        } => {
+            let tags = select_tags(&|cmd| {
+                for commit in &contains {
+                    cmd.arg(format!("--contains={commit}"));
+                }
+                if let Some(merged) = &merged {
+                    cmd.arg(format!("--merged={merged}"));
+                }
+                cmd
+            })?;
+            (tags, files)
+        }
+        Subcommand::Releases {
+            pattern,
+            limit,
+            files: FileSelection { files },
+        } => {
+            if *output != OutputMode::Text {
+                return Err(anyhow!(
+                    "`releases` shows a graph for each range, so it only supports `--output text`"
+                )
+                .into());
+            }
+            let paths = files.iter().map(|f| f.as_os_str()).collect::<Vec<_>>();
+            releases = Some(release_ranges(&pattern, limit, &paths)?);
+            (Vec::new(), files)
+        }
+        Subcommand::Refs {
+            patterns,
+            max_refs,
+            files: FileSelection { files },
+        } => {
+            let refs = select_refs(&patterns)?;
+            check_max_refs(&refs, max_refs)?;
+            decorate_refs = patterns;
+            (refs, files)
+        }
+        Subcommand::Root {
+            count,
+            all,
+            files: FileSelection { files },
+        } => {
+            let (commits, boundary) = select_roots(count, all)?;
+            since = Some(boundary);
+            (commits, files)
+        }
+        Subcommand::SelectFromLog {
+            pattern,
+            tips,
+            regexp_ignore_case,
+            files: FileSelection { files },
+        } => {
+            let (commits, boundary) = select_from_log(&pattern, &tips, regexp_ignore_case)?;
+            since = Some(boundary);
+            (commits, files)
+        }
+        Subcommand::Graph {
+            branches,
+            files: FileSelection { files },
+        } => {
+            let branches = if branches.is_empty() {
+                let config = PresetConfig {
+                    select_upstreams: true,
+                    select_pushes: true,
+                    select_last_tag: false,
+                    select_remotes: false,
                 };
-                (branches, files)
+                select_branches(&config, &|cmd| cmd)?
+            } else {
+                branches
+            };
+            (branches, files)
+        }
+        Subcommand::Select {
+            branches,
+            range_diff,
+            base,
+            creation_factor,
+            files: FileSelection { files },
+        } => {
+            if let Some(range_diff) = range_diff {
+                let [old, new] = &range_diff[..] else {
+                    unreachable!("`--range-diff` takes exactly two values");
+                };
+                if !files.is_empty() {
+                    log::warn!("`--range-diff` ignores file paths");
+                }
+                let render = resolve_render_config(
+                    *graph_style,
+                    *color,
+                    (*paginate || *no_pager).then_some(*paginate),
+                )?;
+                return git_glimpse::range_diff(
+                    &resolve_base(base)?,
+                    old,
+                    new,
+                    creation_factor,
+                    &render,
+                );
             }
-            Subcommand::Locals {
-                config,
-                files: FileSelection { files },
-            } => (branches(&config, &|cmd| cmd)?, files),
-            Subcommand::Select {
-                branches,
-                files: FileSelection { files },
-            } => (branches, files),
+            (branches, files)
+        }
+    };
+    let branches = if *ignore_missing {
+        drop_missing(branches)?
+    } else {
+        branches
+    };
+    log::debug!("showing graph for branches {branches:?}");
+    let options = GraphOptions {
+        format: format.clone(),
+        responsive_format: *responsive_format,
+        committer_date: *use_committer_date,
+        show_signature: *show_signature,
+        show_parents: *show_parents,
+        merge_base,
+        since: match (since, since_merge) {
+            (Some(since), _) => Some(since),
+            (None, Some(merged)) => Some(vec![merge_commit_of(merged, &resolve_base(None)?)?]),
+            (None, None) => since_ref.clone().map(|since_ref| vec![since_ref]),
+        },
+        decorate_all: *decorate_all,
+        decorate_refs,
+        mark_head: *mark_head,
+        mark_base: *mark_base,
+        plain: *plain,
+        markers,
+        color_by_author: *color_by_author,
+        legend: *legend,
+        all_paths: *all_paths,
+        author: mine.then(author_pattern_for_self).transpose()?,
+        exclude_authors: exclude_author.clone(),
+        encoding: encoding.clone(),
+        max_depth: *graph_max_depth,
+        max_count: *max_count,
+        dim_older_than: *dim_older_than,
+        hide_older_than: *hide_older_than,
+        since_date: since_date.clone(),
+        until_date: until_date.clone(),
+        plain_decorations: *no_color_decorate,
+        assume_yes: *yes,
+        require_nonempty: *require_nonempty,
+        truncate_subject: *truncate_subject,
+        relative_locale: *relative_locale,
+        check_signoff: *check_signoff,
+        mark_true_merges: *no_ff_only_merges,
+        mark_unpushed: *mark_unpushed,
+        stack_position: *stack_position,
+        resolve_fixups: *resolve_fixups,
+        wip: *wip,
+        progressive: *progressive,
+        render: resolve_render_config(
+            *graph_style,
+            *color,
+            (*paginate || *no_pager).then_some(*paginate),
+        )?,
+    };
+    if let Some(releases) = releases {
+        // Paging each range separately would make all but the last one disappear.
+        env::set_var("GIT_PAGER", "cat");
+        for (idx, ReleaseRange { from, to, commits }) in releases.iter().enumerate() {
+            if idx != 0 {
+                println!();
+            }
+            let short = |tag: &'_ String| tag.strip_prefix("refs/tags/").unwrap_or(tag).to_owned();
+            let plural = if *commits == 1 { "" } else { "s" };
+            println!("{} → {} ({commits} commit{plural})", short(from), short(to));
+            let options = GraphOptions {
+                since: Some(vec![from.clone()]),
+                merge_base: None,
+                ..options.clone()
+            };
+            show_graph(&options, [to.as_str()], files.iter().map(|f| f.as_os_str()))?;
+        }
+        return Ok(());
+    }
+    let branches = branches.iter().map(|s| s.as_str());
+    let files = files.iter().map(|f| f.as_os_str());
+    match output {
+        OutputMode::Text => (),
+        OutputMode::Json => return show_graph_json(&options, branches, files),
+        OutputMode::Dot => return show_dot(&options, branches, files),
+        OutputMode::Mermaid => return show_mermaid(&options, branches, files),
+        OutputMode::Tsv => return show_tsv(&options, branches, files, tsv_fields),
+        OutputMode::Digest => return show_digest(&options, branches, files),
+    }
+    #[cfg(feature = "interactive")]
+    if *interactive {
+        return git_glimpse::show_graph_interactive(&options, branches, files);
+    }
+    show_graph(&options, branches, files)?;
+    if let Some(epilogue) = epilogue {
+        println!();
+        print!("{epilogue}");
+    }
+    Ok(())
+}
+
+/// Print a completion script of this binary's command-line interface for `shell`.
+fn print_completions(shell: Shell) {
+    clap_complete::generate(
+        shell,
+        &mut Args::command(),
+        env!("CARGO_BIN_NAME"),
+        &mut io::stdout(),
+    );
+}
+
+/// The file from which [`load_config_file`] loads configs by default, relative to the root of the
+/// working tree.
+const DEFAULT_CONFIG_FILE: &str = ".glimpse.toml";
+
+/// Fail if more than `max_refs` refs were selected, unless `max_refs` is 0.
+fn check_max_refs(refs: &[String], max_refs: usize) -> git_glimpse::Result<()> {
+    if max_refs != 0 && refs.len() > max_refs {
+        return Err(anyhow!(
+            "{} refs were selected, which exceeds the maximum of {max_refs}; consider using \
+            `select` with a smaller set of refs, or raising `--max-refs`",
+            refs.len()
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Parse a `key=value` pair for `--git-config`.
+fn parse_config_override(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| "expected `KEY=VALUE`".to_owned())?;
+    // Git requires keys to have at least a section and a name.
+    match key.split_once('.') {
+        Some((section, name)) if !section.is_empty() && !name.is_empty() => (),
+        _ => return Err(format!("invalid key {key:?}; expected `section.name`")),
+    }
+    Ok((key.to_owned(), value.to_owned()))
+}
+
+/// Load `glimpse.*` configs from the TOML file at `path`, or else from [`DEFAULT_CONFIG_FILE`] at
+/// the root of the working tree, if it exists.
+fn load_config_file(path: Option<&Path>) -> git_glimpse::Result<Vec<(String, String)>> {
+    let path = match path {
+        Some(path) => path.to_owned(),
+        None => match worktree_root()? {
+            Some(root) if root.join(DEFAULT_CONFIG_FILE).is_file() => {
+                root.join(DEFAULT_CONFIG_FILE)
+            }
+            _ => return Ok(Vec::new()),
+        },
+    };
+    log::debug!("loading configs from {}", path.display());
+    let contents =
+        fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    parse_config_file(&contents, &path)
+}
+
+/// Parse the `contents` of the config file at `path`, as for [`load_config_file`].
+fn parse_config_file(contents: &str, path: &Path) -> git_glimpse::Result<Vec<(String, String)>> {
+    let table = contents
+        .parse::<toml::Table>()
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    let mut configs = Vec::new();
+    for (name, value) in table {
+        let values = match value {
+            toml::Value::Array(values) => values,
+            value => vec![value],
         };
-        log::debug!("showing graph for branches {branches:?}");
-        show_graph(
-            format,
-            branches.iter().map(|s| s.as_str()),
-            files.iter().map(|f| f.as_os_str()),
+        for value in values {
+            let value = match value {
+                toml::Value::String(value) => value,
+                toml::Value::Integer(value) => value.to_string(),
+                toml::Value::Float(value) => value.to_string(),
+                toml::Value::Boolean(value) => value.to_string(),
+                _ => {
+                    return Err(anyhow!(
+                        "unsupported value for `{name}` in {}; expected a string, number, \
+                        boolean, or an array of them",
+                        path.display()
+                    )
+                    .into())
+                }
+            };
+            configs.push((format!("glimpse.{name}"), value));
+        }
+    }
+    Ok(configs)
+}
+
+/// Pass `defaults` to every Git call we make, like [`override_git_config`], but before any
+/// already passed to us via `GIT_CONFIG_COUNT`, so that those take precedence.
+fn underlay_git_config(defaults: &[(String, String)]) {
+    if defaults.is_empty() {
+        return;
+    }
+    let count = env::var("GIT_CONFIG_COUNT")
+        .ok()
+        .and_then(|count| count.parse::<usize>().ok())
+        .unwrap_or(0);
+    for idx in (0..count).rev() {
+        for part in ["KEY", "VALUE"] {
+            if let Some(value) = env::var_os(format!("GIT_CONFIG_{part}_{idx}")) {
+                env::set_var(format!("GIT_CONFIG_{part}_{}", idx + defaults.len()), value);
+            }
+        }
+    }
+    for (idx, (key, value)) in defaults.iter().enumerate() {
+        env::set_var(format!("GIT_CONFIG_KEY_{idx}"), key);
+        env::set_var(format!("GIT_CONFIG_VALUE_{idx}"), value);
+    }
+    env::set_var("GIT_CONFIG_COUNT", (count + defaults.len()).to_string());
+}
+
+/// Pass `overrides` to every Git call we make, after any already passed to us via
+/// `GIT_CONFIG_COUNT` (see `git config`).
+fn override_git_config(overrides: &[(String, String)]) {
+    if overrides.is_empty() {
+        return;
+    }
+    let count = env::var("GIT_CONFIG_COUNT")
+        .ok()
+        .and_then(|count| count.parse::<usize>().ok())
+        .unwrap_or(0);
+    for (idx, (key, value)) in (count..).zip(overrides) {
+        env::set_var(format!("GIT_CONFIG_KEY_{idx}"), key);
+        env::set_var(format!("GIT_CONFIG_VALUE_{idx}"), value);
+    }
+    env::set_var("GIT_CONFIG_COUNT", (count + overrides.len()).to_string());
+}
+
+/// Parse a duration like `30d`, for options like `--dim-older-than`.
+fn parse_age(s: &str) -> Result<Duration, String> {
+    let unit_idx = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| "missing unit, like `d` for days".to_owned())?;
+    let (count, unit) = s.split_at(unit_idx);
+    let count = count
+        .parse::<u64>()
+        .map_err(|e| format!("invalid count {count:?}: {e}"))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(format!(
+                "unrecognized unit {unit:?}; expected `s`, `m`, `h`, `d`, or `w`"
+            ))
+        }
+    };
+    Ok(Duration::from_secs(count.saturating_mul(unit_secs)))
+}
+
+/// An entry of the `glimpse.dashboard` config.
+#[derive(Debug, Parser)]
+struct DashboardSection {
+    #[clap(subcommand)]
+    subcommand: Subcommand,
+}
+
+fn show_dashboard(args: &Args) -> git_glimpse::Result<()> {
+    let mut sections = git_config_all("glimpse.dashboard")?;
+    if sections.is_empty() {
+        sections = vec!["stack".to_owned(), "locals".to_owned()];
+    }
+    // Paging each section separately would make all but the last one disappear.
+    env::set_var("GIT_PAGER", "cat");
+    for (idx, section) in sections.iter().enumerate() {
+        let words = shell_words::split(section)
+            .with_context(|| format!("failed to split `glimpse.dashboard` entry {section:?}"))?;
+        let DashboardSection { subcommand } = DashboardSection::try_parse_from(
+            iter::once("glimpse.dashboard".to_owned()).chain(words),
         )
-    })
+        .with_context(|| format!("invalid `glimpse.dashboard` entry {section:?}"))?;
+        if let Subcommand::Dashboard = subcommand {
+            return Err(anyhow!("`glimpse.dashboard` entries can't be `dashboard`").into());
+        }
+        if idx != 0 {
+            println!();
+        }
+        println!("==> {section} <==");
+        show(args, subcommand)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> git_glimpse::Result<Vec<(String, String)>> {
+        parse_config_file(contents, Path::new(".glimpse.toml"))
+    }
+
+    fn parse_error(contents: &str) -> String {
+        match parse(contents) {
+            Err(git_glimpse::Error::Other { source }) => format!("{source:#}"),
+            other => panic!("expected an error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn config_file_values_become_glimpse_configs() {
+        let configs = parse(
+            "base = \"trunk\"\n\
+            warnCommitThreshold = 50\n\
+            autoDecorate = false\n\
+            exclude = [\"wip/*\", \"tmp\"]\n",
+        )
+        .unwrap();
+        let configs = configs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            configs,
+            [
+                ("glimpse.autoDecorate", "false"),
+                ("glimpse.base", "trunk"),
+                ("glimpse.exclude", "wip/*"),
+                ("glimpse.exclude", "tmp"),
+                ("glimpse.warnCommitThreshold", "50"),
+            ]
+        );
+    }
+
+    #[test]
+    fn config_file_rejects_tables_and_invalid_toml() {
+        let error = parse_error("[pretty]\nshort = \"%h\"\n");
+        assert!(error.contains("unsupported value for `pretty`"), "{error}");
+        let error = parse_error("base = [\"main\", [\"nested\"]]\n");
+        assert!(error.contains("unsupported value for `base`"), "{error}");
+        let error = parse_error("base = main\n");
+        assert!(error.contains("failed to parse .glimpse.toml"), "{error}");
+    }
 }
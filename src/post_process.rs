@@ -0,0 +1,812 @@
+//! Post-processing of `git log --graph` output, for embellishments that Git can't render by
+//! itself.
+
+use std::{
+    env,
+    io::{self, IsTerminal, Write},
+    process::{Command, Stdio},
+};
+
+use anyhow::Context;
+use ezcmd::EasyCommand;
+
+use crate::{stdout_lines, CommitMarker, Error, MarkerColor, RelativeLocale, Result};
+
+/// Marks the start of a commit's object name in output marked with [`mark_commits`].
+const COMMIT_START: char = '\x1e';
+/// Marks the end of a commit's committer timestamp in output marked with [`mark_commits`].
+const COMMIT_END: char = '\x1f';
+
+/// Extend a custom `format` so that [`LogLine::parse`] can recover the object name and committer
+/// timestamp of each commit from its first line of output.
+///
+/// Returns `None` for named formats (i.e., `oneline`), which can't be extended.
+pub(crate) fn mark_commits(format: &str) -> Option<String> {
+    let (prefix, format) = split_custom_format(format)?;
+    Some(format!("{prefix}%x1e%H %ct%x1f{format}"))
+}
+
+/// Append `suffix` to a custom `format`.
+///
+/// Returns `None` for named formats (i.e., `oneline`), which can't be extended.
+pub(crate) fn append_to_format(format: &str, suffix: &str) -> Option<String> {
+    let (prefix, format) = split_custom_format(format)?;
+    Some(format!("{prefix}{format}{suffix}"))
+}
+
+/// Marks the start of the email of an author marked with [`mark_authors`].
+const AUTHOR_START: char = '\x02';
+/// Marks the end of the email, and start of the field, of an author marked with [`mark_authors`].
+const AUTHOR_FIELD: char = '\x03';
+/// Marks the end of the field of an author marked with [`mark_authors`].
+const AUTHOR_END: char = '\x04';
+
+/// Extend a custom `format` so that [`color_authors`] can identify each author name or email
+/// placeholder (i.e., `%an` or `%ae`) in its output, and the author it belongs to.
+///
+/// Returns `None` if `format` is named, or has no such placeholders.
+pub(crate) fn mark_authors(format: &str) -> Option<String> {
+    wrap_placeholders(
+        format,
+        &["%an", "%aN", "%ae", "%aE", "%al", "%aL"],
+        |author| format!("%x02%ae%x03{author}%x04"),
+    )
+}
+
+/// Color the authors in `line`, marked by [`mark_authors`], with a color determined by their
+/// email.
+pub(crate) fn color_authors(line: &str) -> String {
+    // Readable colors from the 256-color palette, which are distinct from each other.
+    const PALETTE: &[u8] = &[
+        33, 39, 41, 69, 75, 105, 113, 141, 149, 167, 173, 179, 185, 203, 209, 214,
+    ];
+
+    let mut colored = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some((before, marked)) = rest.split_once(AUTHOR_START) {
+        colored.push_str(before);
+        let Some((email, field)) = marked.split_once(AUTHOR_FIELD) else {
+            rest = marked;
+            break;
+        };
+        let (field, after) = field.split_once(AUTHOR_END).unwrap_or((field, ""));
+        // FNV-1a, which is stable across versions of Rust, unlike `DefaultHasher`.
+        let hash = email.bytes().fold(0x811c9dc5_u32, |hash, byte| {
+            (hash ^ u32::from(byte)).wrapping_mul(0x01000193)
+        });
+        let color = PALETTE[hash as usize % PALETTE.len()];
+        // Only reset the foreground color, keeping other attributes, like dimming.
+        colored.push_str(&format!("\x1b[38;5;{color}m{field}\x1b[39m"));
+        rest = after;
+    }
+    colored.push_str(rest);
+    colored
+}
+
+/// Marks the start of a subject marked with [`mark_subjects`].
+const SUBJECT_START: char = '\x05';
+/// Marks the end of a subject marked with [`mark_subjects`].
+const SUBJECT_END: char = '\x06';
+
+/// Extend a custom `format` so that [`truncate_subjects`] can identify each subject placeholder
+/// (i.e., `%s`) in its output.
+///
+/// Returns `None` if `format` is named, or has no such placeholders.
+pub(crate) fn mark_subjects(format: &str) -> Option<String> {
+    wrap_placeholders(format, &["%s"], |subject| format!("%x05{subject}%x06"))
+}
+
+/// Truncate the subjects in `line`, marked by [`mark_subjects`], to `max_chars` characters,
+/// ending truncated subjects with an ellipsis.
+pub(crate) fn truncate_subjects(line: &str, max_chars: usize) -> String {
+    let mut truncated = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some((before, marked)) = rest.split_once(SUBJECT_START) {
+        truncated.push_str(before);
+        let (subject, after) = marked.split_once(SUBJECT_END).unwrap_or((marked, ""));
+        if subject.chars().count() > max_chars {
+            truncated.extend(subject.chars().take(max_chars.saturating_sub(1)));
+            truncated.push('…');
+        } else {
+            truncated.push_str(subject);
+        }
+        rest = after;
+    }
+    truncated.push_str(rest);
+    truncated
+}
+
+/// Marks the start of an object name marked with [`mark_hashes`].
+const HASH_START: char = '\x0e';
+/// Marks the end of an object name marked with [`mark_hashes`].
+const HASH_END: char = '\x0f';
+
+/// Extend a custom `format` so that [`link_hashes`] can identify each commit object name
+/// placeholder (i.e., `%h` or `%H`) in its output.
+///
+/// Returns `None` if `format` is named, or has no such placeholders.
+pub(crate) fn mark_hashes(format: &str) -> Option<String> {
+    wrap_placeholders(format, &["%h", "%H"], |hash| format!("%x0e{hash}%x0f"))
+}
+
+/// Make the object names in `line`, marked by [`mark_hashes`], links to `url` (see OSC 8).
+pub(crate) fn link_hashes(line: &str, url: Option<&str>) -> String {
+    let mut linked = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some((before, marked)) = rest.split_once(HASH_START) {
+        linked.push_str(before);
+        let (hash, after) = marked.split_once(HASH_END).unwrap_or((marked, ""));
+        match url {
+            Some(url) => linked.push_str(&format!("\x1b]8;;{url}\x1b\\{hash}\x1b]8;;\x1b\\")),
+            None => linked.push_str(hash),
+        }
+        rest = after;
+    }
+    linked.push_str(rest);
+    linked
+}
+
+/// Marks the start of a timestamp marked with [`mark_relative_dates`].
+const DATE_START: char = '\x10';
+/// Marks the end of a timestamp marked with [`mark_relative_dates`].
+const DATE_END: char = '\x11';
+
+/// Extend a custom `format` so that [`localize_relative_dates`] can identify each relative date
+/// placeholder (i.e., `%ar` or `%cr`) in its output, replacing them with the Unix timestamps they
+/// were computed from.
+///
+/// Returns `None` if `format` is named, or has no such placeholders.
+pub(crate) fn mark_relative_dates(format: &str) -> Option<String> {
+    wrap_placeholders(format, &["%ar", "%cr"], |date| {
+        let timestamp = if date == "%ar" { "%at" } else { "%ct" };
+        format!("%x10{timestamp}%x11")
+    })
+}
+
+/// Replace the timestamps in `line`, marked by [`mark_relative_dates`], with how long before
+/// `now` they were in `locale`'s language.
+pub(crate) fn localize_relative_dates(line: &str, locale: RelativeLocale, now: i64) -> String {
+    let mut localized = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some((before, marked)) = rest.split_once(DATE_START) {
+        localized.push_str(before);
+        let (timestamp, after) = marked.split_once(DATE_END).unwrap_or((marked, ""));
+        match timestamp.parse::<i64>() {
+            Ok(timestamp) => localized.push_str(&relative_date(now - timestamp, locale)),
+            Err(_) => localized.push_str(timestamp),
+        }
+        rest = after;
+    }
+    localized.push_str(rest);
+    localized
+}
+
+/// Describe an age of `secs` seconds in `locale`'s language, with units chosen like
+/// `git log --date=relative` does, except that years aren't broken down into months.
+fn relative_date(secs: i64, locale: RelativeLocale) -> String {
+    // Singular and plural forms of each unit, from seconds to years.
+    const UNITS_DE: [(&str, &str); 7] = [
+        ("Sekunde", "Sekunden"),
+        ("Minute", "Minuten"),
+        ("Stunde", "Stunden"),
+        ("Tag", "Tagen"),
+        ("Woche", "Wochen"),
+        ("Monat", "Monaten"),
+        ("Jahr", "Jahren"),
+    ];
+    const UNITS_EN: [(&str, &str); 7] = [
+        ("second", "seconds"),
+        ("minute", "minutes"),
+        ("hour", "hours"),
+        ("day", "days"),
+        ("week", "weeks"),
+        ("month", "months"),
+        ("year", "years"),
+    ];
+    const UNITS_ES: [(&str, &str); 7] = [
+        ("segundo", "segundos"),
+        ("minuto", "minutos"),
+        ("hora", "horas"),
+        ("día", "días"),
+        ("semana", "semanas"),
+        ("mes", "meses"),
+        ("año", "años"),
+    ];
+    const UNITS_FR: [(&str, &str); 7] = [
+        ("seconde", "secondes"),
+        ("minute", "minutes"),
+        ("heure", "heures"),
+        ("jour", "jours"),
+        ("semaine", "semaines"),
+        ("mois", "mois"),
+        ("an", "ans"),
+    ];
+
+    let secs = secs.max(0);
+    let (unit, count) = match secs {
+        0..=89 => (0, secs),
+        90..=5_399 => (1, (secs + 30) / 60),
+        5_400..=129_599 => (2, (secs + 1_800) / 3_600),
+        _ => match (secs + 43_200) / 86_400 {
+            days @ ..=13 => (3, days),
+            days @ 14..=69 => (4, (days + 3) / 7),
+            days @ 70..=364 => (5, (days + 15) / 30),
+            days => (6, (days + 183) / 365),
+        },
+    };
+    let units = match locale {
+        RelativeLocale::De => &UNITS_DE,
+        RelativeLocale::En => &UNITS_EN,
+        RelativeLocale::Es => &UNITS_ES,
+        RelativeLocale::Fr => &UNITS_FR,
+    };
+    let (singular, plural) = units[unit];
+    let unit = if count == 1 { singular } else { plural };
+    match locale {
+        RelativeLocale::De => format!("vor {count} {unit}"),
+        RelativeLocale::En => format!("{count} {unit} ago"),
+        RelativeLocale::Es => format!("hace {count} {unit}"),
+        RelativeLocale::Fr => format!("il y a {count} {unit}"),
+    }
+}
+
+/// Replace each of `placeholders` in a custom `format` with what `wrap` returns for it, leaving
+/// escaped `%`s alone.
+///
+/// Returns `None` if `format` is named, or has no such placeholders.
+fn wrap_placeholders(
+    format: &str,
+    placeholders: &[&str],
+    wrap: impl Fn(&str) -> String,
+) -> Option<String> {
+    split_custom_format(format)?;
+    let mut marked = String::with_capacity(format.len());
+    let mut rest = format;
+    while let Some(idx) = rest.find('%') {
+        let (before, placeholder) = rest.split_at(idx);
+        marked.push_str(before);
+        let len = if placeholder.starts_with("%%") {
+            2
+        } else if let Some(found) = placeholders.iter().find(|p| placeholder.starts_with(**p)) {
+            marked.push_str(&wrap(found));
+            rest = &placeholder[found.len()..];
+            continue;
+        } else {
+            1
+        };
+        marked.push_str(&placeholder[..len]);
+        rest = &placeholder[len..];
+    }
+    marked.push_str(rest);
+    (marked != format).then_some(marked)
+}
+
+/// Split a custom `format` into its `format:` or `tformat:` prefix (if any) and the rest.
+fn split_custom_format(format: &str) -> Option<(&str, &str)> {
+    let (prefix, format) = ["format:", "tformat:"]
+        .into_iter()
+        .find_map(|prefix| format.strip_prefix(prefix).map(|rest| (prefix, rest)))
+        .unwrap_or(("", format));
+    (!prefix.is_empty() || format.contains('%')).then_some((prefix, format))
+}
+
+/// A single line of `git log --graph` output.
+#[derive(Debug)]
+pub(crate) struct LogLine {
+    /// The line, minus any markers inserted by [`mark_commits`].
+    pub text: String,
+    /// The object name of the commit this line starts, if any. This may be abbreviated if the
+    /// output was not marked with [`mark_commits`].
+    pub commit: Option<String>,
+    /// The committer timestamp of [`Self::commit`], in seconds since the Unix epoch. Only
+    /// available if the output was marked with [`mark_commits`].
+    pub committed: Option<i64>,
+}
+
+impl LogLine {
+    pub(crate) fn parse(line: &str, marked: bool) -> Self {
+        if marked {
+            Self::parse_marked(line)
+        } else {
+            Self::parse_named(line)
+        }
+    }
+
+    fn parse_marked(line: &str) -> Self {
+        let parsed = line.split_once(COMMIT_START).and_then(|(graph, rest)| {
+            let (marker, rest) = rest.split_once(COMMIT_END)?;
+            let (commit, committed) = marker.split_once(' ')?;
+            Some(Self {
+                text: format!("{graph}{rest}"),
+                commit: Some(commit.to_owned()),
+                committed: committed.parse().ok(),
+            })
+        });
+        parsed.unwrap_or_else(|| Self {
+            text: line.to_owned(),
+            commit: None,
+            committed: None,
+        })
+    }
+
+    /// Git's named formats all include the (possibly abbreviated) object name of a commit in one
+    /// of the first two words of its `*` line in the graph, like `commit <hash>` in `medium`, or
+    /// `<hash> <subject>` in `oneline`.
+    fn parse_named(line: &str) -> Self {
+        let plain = strip_ansi(line);
+        let content = plain.trim_start_matches(is_graph_char);
+        let graph = &plain[..plain.len() - content.len()];
+        let commit = graph
+            .contains('*')
+            .then(|| {
+                content
+                    .split(|c: char| c.is_whitespace() || c == '(')
+                    .take(2)
+                    .find(|word| word.len() >= 4 && word.chars().all(|c| c.is_ascii_hexdigit()))
+            })
+            .flatten()
+            .map(ToOwned::to_owned);
+        Self {
+            text: line.to_owned(),
+            commit,
+            committed: None,
+        }
+    }
+
+    /// Whether this line starts the commit named `oid`.
+    pub(crate) fn is_commit(&self, oid: &str) -> bool {
+        self.commit
+            .as_deref()
+            .is_some_and(|commit| oid.starts_with(commit))
+    }
+}
+
+/// Insert a line indicating `changes` uncommitted changes in the working tree above the line of
+/// the commit at `head`, continuing the graph of that line. Returns whether that line was found.
+pub(crate) fn insert_working_tree(lines: &mut Vec<LogLine>, head: &str, changes: usize) -> bool {
+    let Some(idx) = lines.iter().position(|line| line.is_commit(head)) else {
+        return false;
+    };
+    let plain = strip_ansi(&lines[idx].text);
+    let graph = plain.find(['*', '●']).map_or("", |end| &plain[..end]);
+    let files = if changes == 1 { "file" } else { "files" };
+    lines.insert(
+        idx,
+        LogLine {
+            text: format!("{graph}+ (working tree) {changes} changed {files}"),
+            commit: None,
+            committed: None,
+        },
+    );
+    true
+}
+
+/// Prefix each line with the markers of the commit it starts (if any), padding all other lines
+/// to keep the graph aligned.
+pub(crate) fn prefix_markers(lines: &mut [LogLine], markers: &[CommitMarker], color: bool) {
+    let prefixes = lines
+        .iter()
+        .map(|line| {
+            markers
+                .iter()
+                .filter(|marker| line.is_commit(&marker.commit))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let width_of = |markers: &[&CommitMarker]| {
+        markers
+            .iter()
+            .map(|marker| marker.marker.chars().count() + 1)
+            .sum::<usize>()
+    };
+    let width = prefixes
+        .iter()
+        .map(|markers| width_of(markers))
+        .max()
+        .unwrap_or(0);
+    if width == 0 {
+        return;
+    }
+    for (line, markers) in lines.iter_mut().zip(prefixes) {
+        let mut prefix = String::new();
+        for CommitMarker {
+            marker,
+            color: marker_color,
+            ..
+        } in &markers
+        {
+            match marker_color.filter(|_| color) {
+                Some(marker_color) => {
+                    prefix += &format!("\x1b[{}m{marker}\x1b[m ", sgr(marker_color));
+                }
+                None => prefix += &format!("{marker} "),
+            }
+        }
+        prefix.extend((width_of(&markers)..width).map(|_| ' '));
+        line.text.insert_str(0, &prefix);
+    }
+}
+
+/// Dim the lines of commits committed before `cutoff`, in seconds since the Unix epoch. All lines
+/// up to the next commit's line are dimmed, too.
+pub(crate) fn dim_commits_before(lines: &mut [LogLine], cutoff: i64) {
+    let mut dim = false;
+    for line in lines {
+        if line.commit.is_some() {
+            dim = line.committed.is_some_and(|committed| committed < cutoff);
+        }
+        if dim {
+            // Resets within the line would otherwise end dimming early.
+            let text = line.text.replace("\x1b[m", "\x1b[m\x1b[2m");
+            line.text = format!("\x1b[2m{text}\x1b[m");
+        }
+    }
+}
+
+/// Redraw the graph at the start of `line` with box-drawing characters.
+///
+/// The graph ends at its first run of three spaces, which Git uses to indent commit messages
+/// and other content, even when the graph itself has gaps between columns.
+///
+/// Octopus merges (i.e., of three or more parents) are drawn by Git as a run of `-` ending in a
+/// `.` after the commit, like `*-.`, with a line for each extra parent below. These are redrawn as
+/// a horizontal line ending in a corner, like `●─╮`. Edges that Git draws as `_` across columns,
+/// like `|_|/`, are redrawn as horizontal lines, too.
+pub(crate) fn unicode_graph(line: &str) -> String {
+    let mut redrawn = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    let mut spaces = 0;
+    let mut previous = ' ';
+    while let Some(&c) = chars.peek() {
+        if c == '\x1b' {
+            // Skip over SGR escape sequences, which color the graph.
+            for c in chars.by_ref() {
+                redrawn.push(c);
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        // Otherwise, these start the message, like a subject of `- dash`.
+        let in_graph = match c {
+            '-' | '.' => matches!(previous, '*' | '-'),
+            '_' => matches!(previous, '|' | '_' | '/' | '\\'),
+            c => is_graph_char(c),
+        };
+        if !in_graph {
+            break;
+        }
+        previous = c;
+        spaces = if c == ' ' { spaces + 1 } else { 0 };
+        if spaces == 3 {
+            break;
+        }
+        redrawn.push(match c {
+            '|' => '│',
+            '/' => '╱',
+            '\\' => '╲',
+            '*' => '●',
+            '-' | '_' => '─',
+            '.' => '╮',
+            c => c,
+        });
+        chars.next();
+    }
+    redrawn.extend(chars);
+    redrawn
+}
+
+/// A key explaining `markers`, along with `effects` describing other embellishments, or `None` if
+/// there's nothing to explain.
+pub(crate) fn legend(markers: &[CommitMarker], effects: &[&str], color: bool) -> Option<String> {
+    let mut entries = Vec::<&CommitMarker>::new();
+    for marker in markers {
+        let seen = entries
+            .iter()
+            .any(|entry| entry.marker == marker.marker && entry.description == marker.description);
+        if !seen {
+            entries.push(marker);
+        }
+    }
+    if entries.is_empty() && effects.is_empty() {
+        return None;
+    }
+    let width = entries
+        .iter()
+        .map(|entry| entry.marker.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut legend = "legend:\n".to_owned();
+    for CommitMarker {
+        marker,
+        color: marker_color,
+        description,
+        ..
+    } in entries
+    {
+        let padding = " ".repeat(width - marker.chars().count());
+        let marker = match marker_color.filter(|_| color) {
+            Some(marker_color) => format!("\x1b[{}m{marker}\x1b[m", sgr(marker_color)),
+            None => marker.clone(),
+        };
+        legend += &format!("  {marker}{padding}  {description}\n");
+    }
+    for effect in effects {
+        legend += &format!("  {effect}\n");
+    }
+    Some(legend)
+}
+
+fn sgr(color: MarkerColor) -> &'static str {
+    match color {
+        MarkerColor::Red => "31",
+        MarkerColor::Green => "32",
+        MarkerColor::Yellow => "33",
+    }
+}
+
+fn is_graph_char(c: char) -> bool {
+    matches!(c, '|' | '/' | '\\' | '_' | '*' | '-' | '.' | ' ')
+}
+
+/// Remove SGR escape sequences (i.e., colors) from `s`.
+pub(crate) fn strip_ansi(s: &str) -> String {
+    let mut stripped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+/// Whether captured `git log` output should be colored, emulating what Git itself would decide
+/// if it were writing directly to `stdout`.
+pub(crate) fn should_color() -> Result<bool> {
+    if !io::stdout().is_terminal() {
+        return Ok(false);
+    }
+    let colorbool = stdout_lines(
+        EasyCommand::new_with("git", |cmd| {
+            cmd.args(["config", "--get-colorbool", "color.diff", "true"])
+        }),
+        true,
+    )?;
+    Ok(colorbool.first().is_some_and(|value| value == "true"))
+}
+
+/// Write `output` to `stdout`, through Git's configured pager if `stdout` is a terminal.
+pub(crate) fn page(output: &[u8]) -> Result<()> {
+    let pager = if io::stdout().is_terminal() {
+        stdout_lines(
+            EasyCommand::new_with("git", |cmd| cmd.args(["var", "GIT_PAGER"])),
+            true,
+        )?
+        .pop()
+        .filter(|pager| !pager.is_empty() && pager != "cat")
+    } else {
+        None
+    };
+    let Some(pager) = pager else {
+        return write_stdout(output);
+    };
+
+    log::debug!("paging output with {pager:?}");
+    // Spawn the pager directly, rather than via `sh -c` (which isn't available everywhere, like on
+    // Windows).
+    let words = match shell_words::split(&pager) {
+        Ok(words) if !words.is_empty() => words,
+        Ok(_) => return write_stdout(output),
+        Err(e) => {
+            log::warn!("failed to parse pager {pager:?}, so not paging: {e}");
+            return write_stdout(output);
+        }
+    };
+    let mut cmd = Command::new(&words[0]);
+    cmd.args(&words[1..]).stdin(Stdio::piped());
+    // Use the same defaults as Git does when it spawns a pager.
+    for (var, default) in [("LESS", "FRX"), ("LV", "-c")] {
+        if env::var_os(var).is_none() {
+            cmd.env(var, default);
+        }
+    }
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            log::warn!("failed to spawn pager {pager:?}, so not paging: {e}");
+            return write_stdout(output);
+        }
+    };
+    let written = ignore_broken_pipe(child.stdin.take().unwrap().write_all(output))
+        .context("failed to write to pager")
+        .map_err(Error::other);
+    let status = child
+        .wait()
+        .context("failed to wait for pager to exit")
+        .map_err(Error::other)?;
+    written?;
+    Error::from_status(status)
+}
+
+/// Write `output` to `stdout` without paging it.
+pub(crate) fn write_stdout(output: &[u8]) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    // Flush, since partial lines would otherwise be written after those of subprocesses.
+    ignore_broken_pipe(stdout.write_all(output).and_then(|()| stdout.flush()))
+        .context("failed to write to `stdout`")
+        .map_err(Error::other)
+}
+
+/// Quitting a pager early (or piping to something like `head`) closes our end of the pipe, which
+/// isn't an error from the user's perspective.
+fn ignore_broken_pipe(res: io::Result<()>) -> io::Result<()> {
+    match res {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        res => res,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unicode_graph_redraws_octopus_merge_of_three() {
+        assert_eq!(
+            unicode_graph("*-.   Merge branches 'a', 'b' and 'c'"),
+            "●─╮   Merge branches 'a', 'b' and 'c'"
+        );
+        assert_eq!(unicode_graph("|\\ \\  "), "│╲ ╲  ");
+        assert_eq!(unicode_graph("| | * c"), "│ │ ● c");
+        assert_eq!(unicode_graph("| * / b"), "│ ● ╱ b");
+    }
+
+    #[test]
+    fn unicode_graph_redraws_octopus_merge_of_four() {
+        assert_eq!(
+            unicode_graph("*---.   Merge branches 'b', 'c' and 'd'"),
+            "●───╮   Merge branches 'b', 'c' and 'd'"
+        );
+        assert_eq!(unicode_graph("|\\ \\ \\  "), "│╲ ╲ ╲  ");
+        assert_eq!(unicode_graph("| | | * d"), "│ │ │ ● d");
+        assert_eq!(unicode_graph("| | * | c"), "│ │ ● │ c");
+        assert_eq!(unicode_graph("| | |/  "), "│ │ │╱  ");
+    }
+
+    #[test]
+    fn unicode_graph_redraws_colored_octopus_merge() {
+        assert_eq!(
+            unicode_graph("*\x1b[33m-\x1b[m\x1b[34m.\x1b[m   Merge"),
+            "●\x1b[33m─\x1b[m\x1b[34m╮\x1b[m   Merge"
+        );
+    }
+
+    #[test]
+    fn unicode_graph_leaves_messages_alone() {
+        // Dashes and dots in subjects aren't part of the graph.
+        assert_eq!(unicode_graph("* fix a-b. c"), "● fix a-b. c");
+        assert_eq!(unicode_graph("|   -.- indented"), "│   -.- indented");
+        assert_eq!(unicode_graph("* - dash subject"), "● - dash subject");
+        assert_eq!(unicode_graph("| * . dot subject"), "│ ● . dot subject");
+        assert_eq!(unicode_graph("* _underscore"), "● _underscore");
+    }
+
+    #[test]
+    fn unicode_graph_redraws_edges_across_columns() {
+        assert_eq!(unicode_graph("| |_|/  "), "│ │─│╱  ");
+        assert_eq!(unicode_graph("|/| |   "), "│╱│ │   ");
+        assert_eq!(unicode_graph("| |_|_|/ "), "│ │─│─│╱ ");
+    }
+
+    const DAY: i64 = 86_400;
+
+    /// Ages just short of and at each switch to a bigger unit: at 90 seconds, 36 hours, and ages
+    /// that round to 14, 70 and 365 days.
+    const BOUNDARIES: [i64; 10] = [
+        89,
+        90,
+        129_599,
+        129_600,
+        14 * DAY - DAY / 2 - 1,
+        14 * DAY - DAY / 2,
+        70 * DAY - DAY / 2 - 1,
+        70 * DAY - DAY / 2,
+        365 * DAY - DAY / 2 - 1,
+        365 * DAY - DAY / 2,
+    ];
+
+    fn assert_boundaries(locale: RelativeLocale, expected: [&str; 10]) {
+        for (secs, expected) in BOUNDARIES.into_iter().zip(expected) {
+            assert_eq!(relative_date(secs, locale), expected, "{secs} seconds");
+        }
+    }
+
+    #[test]
+    fn relative_date_boundaries_de() {
+        assert_boundaries(
+            RelativeLocale::De,
+            [
+                "vor 89 Sekunden",
+                "vor 2 Minuten",
+                "vor 36 Stunden",
+                "vor 2 Tagen",
+                "vor 13 Tagen",
+                "vor 2 Wochen",
+                "vor 10 Wochen",
+                "vor 2 Monaten",
+                "vor 12 Monaten",
+                "vor 1 Jahr",
+            ],
+        );
+    }
+
+    #[test]
+    fn relative_date_boundaries_en() {
+        assert_boundaries(
+            RelativeLocale::En,
+            [
+                "89 seconds ago",
+                "2 minutes ago",
+                "36 hours ago",
+                "2 days ago",
+                "13 days ago",
+                "2 weeks ago",
+                "10 weeks ago",
+                "2 months ago",
+                "12 months ago",
+                "1 year ago",
+            ],
+        );
+    }
+
+    #[test]
+    fn relative_date_boundaries_es() {
+        assert_boundaries(
+            RelativeLocale::Es,
+            [
+                "hace 89 segundos",
+                "hace 2 minutos",
+                "hace 36 horas",
+                "hace 2 días",
+                "hace 13 días",
+                "hace 2 semanas",
+                "hace 10 semanas",
+                "hace 2 meses",
+                "hace 12 meses",
+                "hace 1 año",
+            ],
+        );
+    }
+
+    #[test]
+    fn relative_date_boundaries_fr() {
+        assert_boundaries(
+            RelativeLocale::Fr,
+            [
+                "il y a 89 secondes",
+                "il y a 2 minutes",
+                "il y a 36 heures",
+                "il y a 2 jours",
+                "il y a 13 jours",
+                "il y a 2 semaines",
+                "il y a 10 semaines",
+                "il y a 2 mois",
+                "il y a 12 mois",
+                "il y a 1 an",
+            ],
+        );
+    }
+
+    #[test]
+    fn relative_date_clamps_future_dates() {
+        assert_eq!(relative_date(-5, RelativeLocale::En), "0 seconds ago");
+    }
+}
@@ -0,0 +1,561 @@
+//! End-to-end tests of the `git-glimpse` binary against scratch repositories.
+
+use std::{
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A scratch Git repository, removed when dropped.
+struct Repo {
+    dir: PathBuf,
+}
+
+impl Repo {
+    /// Create an empty repository whose initial branch is `main`.
+    fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let dir = env::temp_dir().join(format!(
+            "git-glimpse-test-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir(&dir).unwrap();
+        let repo = Self { dir };
+        repo.git(&["init", "--quiet", "--initial-branch=main"]);
+        repo
+    }
+
+    fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Isolate `cmd` from the user's configuration, and give it an identity.
+    fn isolate<'a>(&self, cmd: &'a mut Command) -> &'a mut Command {
+        cmd.current_dir(&self.dir)
+            .env("GIT_CONFIG_GLOBAL", "/dev/null")
+            .env("GIT_CONFIG_NOSYSTEM", "1")
+            .env("GIT_AUTHOR_NAME", "A U Thor")
+            .env("GIT_AUTHOR_EMAIL", "author@example.com")
+            .env("GIT_COMMITTER_NAME", "C O Mitter")
+            .env("GIT_COMMITTER_EMAIL", "committer@example.com")
+            .env_remove("GIT_DIR")
+            .env_remove("GIT_WORK_TREE")
+            .env_remove("GIT_GLIMPSE_GIT")
+    }
+
+    /// Run `git` with `args`, returning its trimmed standard output.
+    #[track_caller]
+    fn git(&self, args: &[&str]) -> String {
+        let output = self
+            .isolate(&mut Command::new("git"))
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap().trim().to_owned()
+    }
+
+    /// Make an empty commit on `HEAD` with `subject`, returning its object name.
+    #[track_caller]
+    fn commit(&self, subject: &str) -> String {
+        self.git(&["commit", "--quiet", "--allow-empty", "-m", subject]);
+        self.git(&["rev-parse", "HEAD"])
+    }
+
+    /// Commit `contents` to the file at `path` on `HEAD` with `subject`.
+    #[track_caller]
+    fn commit_file(&self, path: &str, contents: &str, subject: &str) {
+        fs::write(self.dir.join(path), contents).unwrap();
+        self.git(&["add", "--", path]);
+        self.git(&["commit", "--quiet", "-m", subject]);
+    }
+
+    /// Run `git-glimpse` with `args`.
+    fn glimpse(&self, args: &[&str]) -> Output {
+        self.isolate(&mut Command::new(env!("CARGO_BIN_EXE_git-glimpse")))
+            .args(args)
+            .output()
+            .unwrap()
+    }
+
+    /// Run `git-glimpse` with `args`, returning its standard output, which it must succeed with.
+    #[track_caller]
+    fn glimpse_ok(&self, args: &[&str]) -> String {
+        let output = self.glimpse(args);
+        assert!(
+            output.status.success(),
+            "`git-glimpse {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+}
+
+impl Drop for Repo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// A repository with `feat` branched from `main`, where each has commits since.
+fn forked() -> Repo {
+    let repo = Repo::new();
+    repo.commit("base");
+    repo.git(&["branch", "feat"]);
+    repo.commit("main 1");
+    repo.git(&["switch", "--quiet", "feat"]);
+    repo.commit("feat 1");
+    repo.commit("feat 2");
+    repo
+}
+
+/// Sign commits in `repo` with a new SSH key, returning `false` if `ssh-keygen` isn't available.
+fn sign_with_ssh(repo: &Repo) -> bool {
+    let key = repo.path().join(".git").join("signing-key");
+    let generated = Command::new("ssh-keygen")
+        .args([
+            "-q",
+            "-t",
+            "ed25519",
+            "-N",
+            "",
+            "-C",
+            "author@example.com",
+            "-f",
+        ])
+        .arg(&key)
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if !generated {
+        return false;
+    }
+    let public_key = fs::read_to_string(key.with_extension("pub")).unwrap();
+    let allowed_signers = repo.path().join(".git").join("allowed-signers");
+    fs::write(
+        &allowed_signers,
+        format!("committer@example.com {public_key}"),
+    )
+    .unwrap();
+    repo.git(&["config", "gpg.format", "ssh"]);
+    repo.git(&["config", "user.signingKey", key.to_str().unwrap()]);
+    repo.git(&[
+        "config",
+        "gpg.ssh.allowedSignersFile",
+        allowed_signers.to_str().unwrap(),
+    ]);
+    repo.git(&["config", "commit.gpgSign", "true"]);
+    true
+}
+
+#[test]
+fn show_signature_composes_with_graph_and_cutoff() {
+    let repo = Repo::new();
+    if !sign_with_ssh(&repo) {
+        eprintln!("skipping, since `ssh-keygen` isn't available");
+        return;
+    }
+    repo.commit("base");
+    repo.git(&["branch", "feat"]);
+    repo.commit("main 1");
+    repo.git(&["switch", "--quiet", "feat"]);
+    repo.commit("feat 1");
+    repo.git(&["merge", "--quiet", "--no-edit", "main"]);
+    repo.commit("feat 2");
+
+    let graph = repo.glimpse_ok(&["--show-signature", "stack"]);
+    assert!(graph.contains("Good \"git\" signature"), "{graph}");
+    assert!(graph.contains("feat 2"), "{graph}");
+    assert!(
+        !graph.contains("base"),
+        "history should be cut off: {graph}"
+    );
+
+    // Signatures shown by configuration must not leak into parsed output.
+    repo.git(&["config", "log.showSignature", "true"]);
+    let graph = repo.glimpse_ok(&["stack"]);
+    assert!(graph.contains("feat 2"), "{graph}");
+    let json = repo.glimpse_ok(&["--output", "json", "stack"]);
+    assert!(!json.contains("signature"), "{json}");
+    assert_eq!(json.matches("\"hash\"").count(), 4, "{json}");
+}
+
+#[test]
+fn many_refs_are_passed_via_stdin() {
+    const BRANCHES: usize = 3000;
+    let repo = Repo::new();
+    repo.commit("base");
+    // Making thousands of commits one process at a time is slow, so import them all at once.
+    let mut stream = String::new();
+    for i in 0..BRANCHES {
+        stream.push_str(&format!(
+            "commit refs/heads/synthetic-branch-{i:04}\n\
+            committer C O Mitter <committer@example.com> 1700000000 +0000\n\
+            data 5\nc{i:04}\n\
+            from refs/heads/main^0\n\n"
+        ));
+    }
+    let mut import = repo
+        .isolate(&mut Command::new("git"))
+        .args(["fast-import", "--quiet"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap();
+    import
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stream.as_bytes())
+        .unwrap();
+    assert!(import.wait().unwrap().success());
+
+    // So many refs are over the default `--max-refs`.
+    let output = repo.glimpse(&["--format=%s", "locals"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("exceeds the maximum of 500"), "{stderr}");
+
+    let graph = repo.glimpse_ok(&["--format=%s", "locals", "--max-refs=0"]);
+    for i in [0, BRANCHES / 2, BRANCHES - 1] {
+        assert!(graph.contains(&format!("c{i:04}")), "missing c{i:04}");
+    }
+    assert_eq!(graph.matches('*').count(), BRANCHES + 1);
+}
+
+#[test]
+fn branches_named_like_options() {
+    let repo = forked();
+    // `git branch` refuses names like these, but they're valid refs all the same.
+    repo.git(&["update-ref", "refs/heads/--stat", "main"]);
+    repo.git(&["update-ref", "refs/heads/-p", "feat"]);
+    repo.git(&["symbolic-ref", "HEAD", "refs/heads/-p"]);
+
+    let graph = repo.glimpse_ok(&["--format=%s%d", "locals"]);
+    assert!(graph.contains("(HEAD -> -p, feat)"), "{graph}");
+    assert!(graph.contains("(main, --stat)"), "{graph}");
+    assert!(!graph.contains("diff --git"), "{graph}");
+
+    let graph = repo.glimpse_ok(&["--format=%s", "stack", "--base=--stat"]);
+    assert!(graph.contains("main 1"), "{graph}");
+    assert!(graph.contains("feat 2"), "{graph}");
+
+    repo.git(&["branch", "feat-old", "feat"]);
+    repo.git(&[
+        "commit",
+        "--quiet",
+        "--amend",
+        "--allow-empty",
+        "-m",
+        "feat 2, amended",
+    ]);
+    let range_diff = repo.glimpse_ok(&[
+        "--color=never",
+        "select",
+        "--base=--stat",
+        "--range-diff",
+        "feat-old",
+        "refs/heads/-p",
+    ]);
+    assert!(range_diff.contains("= 1:"), "{range_diff}");
+    assert!(range_diff.contains("feat 2, amended"), "{range_diff}");
+}
+
+#[test]
+fn color_choice_applies_to_auto_colors_of_formats() {
+    let repo = forked();
+    let format = "--format=%C(auto)%h%d %s";
+    let colored = repo.glimpse_ok(&["--color=always", format, "stack"]);
+    assert!(colored.contains("\x1b["), "{colored:?}");
+    let plain = repo.glimpse_ok(&["--color=never", format, "stack"]);
+    assert!(!plain.contains('\x1b'), "{plain:?}");
+    assert!(plain.contains("(HEAD -> feat) feat 2"), "{plain:?}");
+}
+
+#[test]
+fn detect_squashed_finds_squash_merges_and_cherry_picks() {
+    let repo = Repo::new();
+    repo.commit_file("base", "base\n", "base");
+    for branch in ["squashed", "picked", "live"] {
+        repo.git(&["switch", "--quiet", "--create", branch, "main"]);
+        repo.commit_file(branch, "1\n", &format!("{branch} 1"));
+        repo.commit_file(branch, "1\n2\n", &format!("{branch} 2"));
+    }
+    repo.git(&["switch", "--quiet", "main"]);
+    repo.git(&["merge", "--quiet", "--squash", "squashed"]);
+    repo.git(&["commit", "--quiet", "-m", "squash of squashed"]);
+    repo.git(&["cherry-pick", "picked~1", "picked"]);
+
+    let objects = || repo.git(&["count-objects"]);
+    let before = objects();
+    let graph = repo.glimpse_ok(&[
+        "--format=%s%d",
+        "locals",
+        "--exclude-merged-into=main",
+        "--detect-squashed",
+    ]);
+    assert_eq!(objects(), before, "no objects should be written");
+    assert!(graph.contains("(live)"), "{graph}");
+    assert!(!graph.contains("(squashed)"), "{graph}");
+    assert!(!graph.contains("(picked)"), "{graph}");
+}
+
+#[test]
+fn history_limits_are_accepted_after_subcommands() {
+    let repo = forked();
+    let before = repo.glimpse_ok(&["--format=%s", "-n", "1", "stack"]);
+    let after = repo.glimpse_ok(&["--format=%s", "stack", "-n", "1"]);
+    assert_eq!(before, after);
+    assert_eq!(after.trim(), "* feat 2");
+
+    let graph = repo.glimpse_ok(&[
+        "--format=%s",
+        "locals",
+        "--since=2000-01-01",
+        "--until=2000-01-02",
+    ]);
+    assert!(!graph.contains("feat"), "{graph}");
+}
+
+#[test]
+fn exclude_applies_to_interleave() {
+    let repo = forked();
+    repo.git(&["branch", "wip/scratch", "main"]);
+    let listing = repo.glimpse_ok(&["locals", "--interleave", "--exclude=wip/*"]);
+    assert!(listing.contains("feat\n"), "{listing}");
+    assert!(!listing.contains("wip/scratch"), "{listing}");
+
+    repo.git(&["config", "glimpse.exclude", "feat"]);
+    let listing = repo.glimpse_ok(&["locals", "--interleave", "--exclude=wip/*"]);
+    assert!(listing.contains("main\n"), "{listing}");
+    assert!(!listing.contains("feat"), "{listing}");
+}
+
+#[test]
+fn exclusion_is_only_reported_when_it_removes_branches() {
+    let repo = forked();
+    let output = repo.glimpse(&["locals", "--exclude=*"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("all branches were excluded"), "{stderr}");
+
+    let output = repo.glimpse(&["locals", "--points-at=main~1", "--exclude=nothing"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("all branches were excluded"), "{stderr}");
+}
+
+#[test]
+fn structured_outputs_select_the_same_commits_as_graphs() {
+    let repo = forked();
+    repo.git(&["config", "user.email", "author@example.com"]);
+    repo.git(&[
+        "commit",
+        "--quiet",
+        "--allow-empty",
+        "--author=O Ther <other@example.com>",
+        "-m",
+        "feat 3",
+    ]);
+    let hashes_in = |text: &str| {
+        let mut hashes = text
+            .split(|c: char| !c.is_ascii_hexdigit())
+            .filter(|word| word.len() == 40)
+            .map(ToOwned::to_owned)
+            .collect::<Vec<_>>();
+        hashes.sort();
+        hashes.dedup();
+        hashes
+    };
+    let cases: &[&[&str]] = &[
+        &["stack"],
+        &["-n", "1", "stack"],
+        &["--since-ref", "main~1", "select", "feat"],
+        &["--since=2000-01-01", "--until=2000-01-02", "stack"],
+        &["--mine", "select", "feat"],
+        &["root", "--count=2"],
+    ];
+    for args in cases {
+        let graph = repo.glimpse_ok(&[&["--format=%H"], *args].concat());
+        let expected = hashes_in(&graph);
+        let json = repo.glimpse_ok(&[&["--output", "json"], *args].concat());
+        let tsv = repo.glimpse_ok(&[&["--output=tsv", "--tsv-fields=hash"], *args].concat());
+        let dot = repo.glimpse_ok(&[&["--output=dot"], *args].concat());
+        for (output, name) in [(&json, "JSON"), (&tsv, "TSV"), (&dot, "DOT")] {
+            // Parents outside of the selection may be listed too, so only compare commits.
+            let commits = if name == "JSON" {
+                output
+                    .lines()
+                    .filter_map(|line| line.split("\"parents\"").next())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            } else {
+                output.clone()
+            };
+            assert_eq!(
+                hashes_in(&commits),
+                expected,
+                "{name} for {args:?}: {output}"
+            );
+        }
+    }
+}
+
+#[test]
+fn bisect_shows_only_commits_above_every_good_commit() {
+    let repo = Repo::new();
+    for i in 1..=10 {
+        repo.commit(&format!("c{i}"));
+    }
+    repo.git(&["bisect", "start", "main", "main~8"]);
+    repo.git(&["bisect", "good", "main~4"]);
+    repo.git(&["bisect", "skip", "main~2"]);
+    let graph = repo.glimpse_ok(&["--format=%s", "bisect"]);
+    assert_eq!(
+        graph, "bad  * c10\n     * c9\nskip * c8\n     * c7\ngood * c6\n",
+        "{graph}"
+    );
+}
+
+#[test]
+fn since_ref_takes_precedence_over_on_base_cutoff() {
+    let repo = Repo::new();
+    for i in 1..=8 {
+        repo.commit(&format!("c{i}"));
+    }
+    let stack = ["stack", "--on-base-mode=recent", "--on-base-count=3"];
+    let graph = repo.glimpse_ok(&[&["--format=%s"][..], &stack].concat());
+    assert_eq!(graph, "* c8\n* c7\n* c6\n");
+    let graph = repo.glimpse_ok(&[&["--format=%s", "--since-ref=main~5"][..], &stack].concat());
+    assert_eq!(graph, "* c8\n* c7\n* c6\n* c5\n* c4\n");
+}
+
+#[test]
+fn unicode_graph_style_redraws_real_octopus_merges() {
+    let repo = Repo::new();
+    for i in 0..3 {
+        repo.commit(&format!("base{i}"));
+    }
+    // Forking from different commits makes Git draw edges across columns, like `|_|/`.
+    for (branch, fork_point) in [("a", "main~1"), ("b", "main~2"), ("c", "main")] {
+        repo.git(&["switch", "--quiet", "--create", branch, fork_point]);
+        repo.commit(branch);
+    }
+    repo.git(&["switch", "--quiet", "main"]);
+    repo.commit("m0");
+    repo.git(&["merge", "--quiet", "--no-edit", "a", "b", "c"]);
+    let graph = repo.glimpse_ok(&[
+        "--graph-style=unicode",
+        "--mark-head",
+        "--format=%s%d",
+        "graph",
+        "main",
+        "a",
+        "b",
+        "c",
+    ]);
+    let expected = [
+        "> ●───╮   Merge branches 'a', 'b' and 'c' (HEAD -> main)",
+        "  │╲ ╲ ╲  ",
+        "  │ │ │ ● c (c)",
+        "  │ │ ● │ b (b)",
+        "  │ ● │ │ a (a)",
+        "  ● │ │ │ m0",
+        "  │ │─│╱  ",
+        "  │╱│ │   ",
+        "  ● │ │ base2",
+        "  │╱ ╱  ",
+        "  ● ╱ base1",
+        "  │╱  ",
+        "  ● base0",
+    ];
+    assert_eq!(graph.lines().collect::<Vec<_>>(), expected, "{graph}");
+}
+
+#[test]
+fn releases_rejects_structured_outputs() {
+    let repo = Repo::new();
+    repo.commit("one");
+    repo.git(&["tag", "v1"]);
+    repo.commit("two");
+    repo.git(&["tag", "v2"]);
+    assert!(repo
+        .glimpse_ok(&["--format=%s", "releases"])
+        .contains("v1 → v2"));
+    let output = repo.glimpse(&["--output=json", "releases"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("only supports `--output text`"), "{stderr}");
+}
+
+#[test]
+fn active_applies_to_remote_tracking_branches() {
+    let repo = Repo::new();
+    let status = repo
+        .isolate(&mut Command::new("git"))
+        .env("GIT_COMMITTER_DATE", "2000-01-01T00:00:00Z")
+        .args(["commit", "--quiet", "--allow-empty", "-m", "old"])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    repo.git(&["update-ref", "refs/remotes/origin/old", "HEAD"]);
+    repo.commit("new");
+    repo.git(&["update-ref", "refs/remotes/origin/new", "HEAD"]);
+    repo.git(&["config", "remote.origin.url", "."]);
+    repo.git(&[
+        "config",
+        "remote.origin.fetch",
+        "+refs/heads/*:refs/remotes/origin/*",
+    ]);
+    repo.git(&["branch", "--quiet", "--set-upstream-to=origin/old"]);
+    for selection in ["--remotes", "--upstreams"] {
+        let graph = repo.glimpse_ok(&["--format=%s%d", "locals", "--active", selection]);
+        assert!(graph.contains("new"), "{selection}: {graph}");
+        assert!(!graph.contains("old"), "{selection}: {graph}");
+    }
+}
+
+#[test]
+fn config_file_precedence() {
+    let repo = Repo::new();
+    repo.commit("one");
+    repo.git(&["config", "glimpse.pretty", "git %s"]);
+    let graph = |env: &[(&str, &str)], args: &[&str]| {
+        let mut glimpse = Command::new(env!("CARGO_BIN_EXE_git-glimpse"));
+        let output = repo
+            .isolate(&mut glimpse)
+            .envs(env.iter().copied())
+            .args(args)
+            .args(["select", "main"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert_eq!(graph(&[], &[]), "* git one\n");
+
+    // The config file takes precedence over Git's own configuration.
+    fs::write(repo.path().join(".glimpse.toml"), "pretty = \"toml %s\"\n").unwrap();
+    assert_eq!(graph(&[], &[]), "* toml one\n");
+    fs::write(repo.path().join("other.toml"), "pretty = \"other %s\"\n").unwrap();
+    assert_eq!(graph(&[], &["--config-file=other.toml"]), "* other one\n");
+
+    // `GIT_CONFIG_*` and `--git-config` take precedence over the config file, the latter last.
+    let env = [
+        ("GIT_CONFIG_COUNT", "1"),
+        ("GIT_CONFIG_KEY_0", "glimpse.pretty"),
+        ("GIT_CONFIG_VALUE_0", "env %s"),
+    ];
+    assert_eq!(graph(&env, &[]), "* env one\n");
+    let flag = ["--git-config=glimpse.pretty=flag %s"];
+    assert_eq!(graph(&env, &flag), "* flag one\n");
+}